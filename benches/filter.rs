@@ -0,0 +1,180 @@
+//! Throughput of filter over a dump generated in memory, run with `cargo bench --bench filter`.
+
+use std::io::{self, BufWriter, Cursor};
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use pgdump_filter::{filter, line_prefix, FilterConfig, Prefix};
+
+const TABLES: usize = 20;
+const ROWS: usize = 5_000;
+
+/// A dump like pg_dump writes it, with a table definition and a copy block per table.
+fn dump(tables: usize, rows: usize) -> Vec<u8> {
+    let mut dump =
+        String::from("--\n-- PostgreSQL database dump\n--\n\nSET statement_timeout = 0;\n");
+    for table in 0..tables {
+        dump.push_str(&format!(
+            "\nCREATE TABLE public.table_{0} (\n    id integer NOT NULL,\n    name text\n);\n\n\
+             ALTER TABLE public.table_{0} OWNER TO postgres;\n",
+            table
+        ));
+    }
+    for table in 0..tables {
+        dump.push_str(&format!(
+            "\n--\n-- Data for Name: table_{0}; Type: TABLE DATA\n--\n\n\
+             COPY public.table_{0} (id, name) FROM stdin;\n",
+            table
+        ));
+        for row in 0..rows {
+            dump.push_str(&format!(
+                "{}\tname of row {} in table {}\n",
+                row, row, table
+            ));
+        }
+        dump.push_str("\\.\n");
+    }
+    dump.into_bytes()
+}
+
+fn bench(c: &mut Criterion, name: &str, dump: &[u8], config: &FilterConfig) {
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes(dump.len() as u64));
+    group.bench_function("filter", |b| {
+        b.iter_batched(
+            || Cursor::new(dump),
+            |reader| filter(reader, io::sink(), config).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+fn filter_benchmark(c: &mut Criterion) {
+    let dump = dump(TABLES, ROWS);
+    // all tables but one dropped, most lines only go through the state machine
+    let exclude_heavy = FilterConfig {
+        included_copy_blocks: vec![String::from("table_0")],
+        ..FilterConfig::default()
+    };
+    bench(c, "exclude_heavy", &dump, &exclude_heavy);
+    // a couple of tables dropped by a glob, most lines written out
+    let include_heavy = FilterConfig {
+        excluded_copy_blocks: vec![String::from("table_*1")],
+        ..FilterConfig::default()
+    };
+    bench(c, "include_heavy", &dump, &include_heavy);
+}
+
+/// Matching the headers of a dump of many small tables against many excluded copy blocks, by
+/// name and by the start of the name.
+fn excluded_blocks_benchmark(c: &mut Criterion) {
+    let dump = dump(1_000, 1);
+    let excluded: Vec<String> = (0..500)
+        .map(|table| format!("table_{}", table * 2))
+        .collect();
+    let by_name = FilterConfig {
+        excluded_copy_blocks: excluded.clone(),
+        ..FilterConfig::default()
+    };
+    bench(c, "excluded_blocks", &dump, &by_name);
+    let by_prefix = FilterConfig {
+        excluded_copy_blocks: excluded,
+        prefix_match: true,
+        ..FilterConfig::default()
+    };
+    bench(c, "excluded_block_prefixes", &dump, &by_prefix);
+}
+
+/// Throughput of writing the whole dump out through an output buffer of several sizes, like
+/// `--write-buffer-kb` does.
+fn write_buffer_benchmark(c: &mut Criterion) {
+    let dump = dump(TABLES, ROWS);
+    let config = FilterConfig::default();
+    let mut group = c.benchmark_group("write_buffer");
+    group.throughput(Throughput::Bytes(dump.len() as u64));
+    for kb in [8, 64, 1024] {
+        group.bench_with_input(BenchmarkId::from_parameter(kb), &kb, |b, &kb| {
+            b.iter_batched(
+                || Cursor::new(&dump),
+                |reader| {
+                    let writer = BufWriter::with_capacity(kb * 1024, io::sink());
+                    filter(reader, writer, &config).unwrap()
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+/// The kind of line as told apart before the automaton, a `starts_with` per prefix in turn.
+fn starts_with_chain(buf: &[u8]) -> Option<Prefix> {
+    const DDL: [&[u8]; 4] = [b"CREATE ", b"ALTER ", b"DROP ", b"COMMENT ON "];
+    match buf {
+        buf if buf.starts_with(b"SELECT pg_catalog.lo_create") => Some(Prefix::LargeObjectCreate),
+        buf if buf.starts_with(b"SELECT pg_catalog.lo_") => Some(Prefix::LargeObject),
+        buf if buf.starts_with(b"SELECT pg_catalog.lowrite") => Some(Prefix::LargeObjectWrite),
+        buf if buf.starts_with(b"\n") || buf.starts_with(b"\r\n") => Some(Prefix::EmptyLine),
+        buf if buf.starts_with(b"SET search_path ")
+            || buf.starts_with(b"SELECT pg_catalog.set_config('search_path',") =>
+        {
+            Some(Prefix::SearchPath)
+        }
+        buf if buf.starts_with(b"SET ") => Some(Prefix::Set),
+        buf if buf.starts_with(b"GRANT ") || buf.starts_with(b"REVOKE ") => Some(Prefix::Grant),
+        buf if buf.starts_with(b"SELECT pg_catalog.setval(") => Some(Prefix::SetVal),
+        buf if buf.starts_with(b"ALTER TABLE ") || buf.starts_with(b"ALTER SEQUENCE ") => {
+            Some(Prefix::AlterTableOrSequence)
+        }
+        buf if buf.starts_with(b"\\.") => Some(Prefix::EndOfCopyBlock),
+        buf if buf.starts_with(b"COPY ") => Some(Prefix::CopyBlock),
+        buf if DDL.iter().any(|ddl| buf.starts_with(ddl)) => Some(Prefix::Ddl),
+        _ => None,
+    }
+}
+
+/// Telling the lines of a dump apart by their start with the automaton and with the chain of
+/// `starts_with` it replaced, which must agree on every line.
+fn prefix_benchmark(c: &mut Criterion) {
+    let mut dump = dump(TABLES, 100);
+    dump.extend_from_slice(
+        b"SET search_path = public;\n\
+          SELECT pg_catalog.set_config('search_path', '', false);\n\
+          GRANT ALL ON TABLE public.table_0 TO me;\n\
+          REVOKE ALL ON SCHEMA public FROM PUBLIC;\n\
+          ALTER SEQUENCE public.s OWNER TO postgres;\n\
+          SELECT pg_catalog.setval('public.s', 1, true);\n\
+          SELECT pg_catalog.lo_create('16397');\n\
+          SELECT pg_catalog.lo_open('16397', 131072);\n\
+          SELECT pg_catalog.lowrite(0, '\\x41');\n\
+          DROP TABLE public.table_0;\n\
+          COMMENT ON TABLE public.table_0 IS 'x';\r\n\r\n",
+    );
+    let lines: Vec<&[u8]> = dump.split_inclusive(|&b| b == b'\n').collect();
+    for line in &lines {
+        assert_eq!(line_prefix(line), starts_with_chain(line), "{:?}", line);
+    }
+    let mut group = c.benchmark_group("prefix");
+    group.throughput(Throughput::Bytes(dump.len() as u64));
+    group.bench_function("aho_corasick", |b| {
+        b.iter(|| lines.iter().filter_map(|line| line_prefix(line)).count())
+    });
+    group.bench_function("starts_with", |b| {
+        b.iter(|| {
+            lines
+                .iter()
+                .filter_map(|line| starts_with_chain(line))
+                .count()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    filter_benchmark,
+    write_buffer_benchmark,
+    prefix_benchmark,
+    excluded_blocks_benchmark
+);
+criterion_main!(benches);
@@ -0,0 +1,104 @@
+//! Copy blocks in binary format, e.g. `COPY public.foo FROM stdin WITH (FORMAT binary);`.
+//!
+//! Binary data can't be processed line by line: any byte sequence can look like a `\.`
+//! terminator or a comment.  Instead the data is read according to the binary copy format, up to
+//! and including its trailer, and passed through verbatim.
+
+use std::io::{self, BufRead, Read, Write};
+
+use crate::Result;
+
+const SIGNATURE: &[u8] = b"PGCOPY\n\xff\r\n\0";
+
+/// Size of the binary data of a copy block.
+pub(crate) struct BinaryData {
+    pub bytes: u64,
+    pub tuples: u64,
+    /// Bytes of the tuples left out, when they are not kept
+    pub dropped_bytes: u64,
+}
+
+/// Copies the binary data of a copy block from `reader` to `writer`.  Without `keep_tuples` only
+/// the signature, header and trailer are written, the copy block is left empty.
+pub(crate) fn copy_binary_data<R: BufRead>(
+    reader: &mut R,
+    writer: &mut dyn Write,
+    keep_tuples: bool,
+) -> Result<BinaryData> {
+    let mut data = BinaryData {
+        bytes: 0,
+        tuples: 0,
+        dropped_bytes: 0,
+    };
+    let mut sink = io::sink();
+    let signature = read_exact(reader, writer, &mut data, SIGNATURE.len())?;
+    if signature != SIGNATURE {
+        return Err("binary copy block does not start with the PGCOPY signature".into());
+    }
+    let _flags = read_exact(reader, writer, &mut data, 4)?;
+    let extension_length = read_exact(reader, writer, &mut data, 4)?;
+    copy_exact(reader, writer, &mut data, be_i32(&extension_length) as u64)?;
+    loop {
+        let field_count = read_exact(reader, &mut sink, &mut data, 2)?;
+        if be_i16(&field_count) == -1 {
+            writer.write_all(&field_count)?;
+            return Ok(data);
+        }
+        let start = data.bytes - field_count.len() as u64;
+        let out: &mut dyn Write = if keep_tuples { writer } else { &mut sink };
+        out.write_all(&field_count)?;
+        for _ in 0..be_i16(&field_count) {
+            let length = be_i32(&read_exact(reader, out, &mut data, 4)?);
+            // a length of -1 is a NULL without any data
+            if length > 0 {
+                copy_exact(reader, out, &mut data, length as u64)?;
+            }
+        }
+        if !keep_tuples {
+            data.dropped_bytes += data.bytes - start;
+        }
+        data.tuples += 1;
+    }
+}
+
+fn read_exact<R: Read>(
+    reader: &mut R,
+    writer: &mut dyn Write,
+    data: &mut BinaryData,
+    n: usize,
+) -> Result<Vec<u8>> {
+    let mut buf = vec![0; n];
+    reader.read_exact(&mut buf).map_err(unexpected_eof)?;
+    writer.write_all(&buf)?;
+    data.bytes += n as u64;
+    Ok(buf)
+}
+
+fn copy_exact<R: Read>(
+    reader: &mut R,
+    writer: &mut dyn Write,
+    data: &mut BinaryData,
+    n: u64,
+) -> Result<()> {
+    let copied = io::copy(&mut reader.by_ref().take(n), writer)?;
+    data.bytes += copied;
+    if copied < n {
+        return Err(unexpected_eof(io::ErrorKind::UnexpectedEof.into()));
+    }
+    Ok(())
+}
+
+fn unexpected_eof(e: io::Error) -> crate::Error {
+    match e.kind() {
+        io::ErrorKind::UnexpectedEof => "unexpected end of input in binary copy block".into(),
+        _ => e.into(),
+    }
+}
+
+fn be_i16(buf: &[u8]) -> i16 {
+    i16::from_be_bytes([buf[0], buf[1]])
+}
+
+fn be_i32(buf: &[u8]) -> i32 {
+    i32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]])
+}
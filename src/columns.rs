@@ -0,0 +1,249 @@
+//! Editing the columns of the copy blocks: dropping them from the column list of the header and
+//! from every row, and masking their values.
+
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+
+use crate::header;
+
+/// The columns of a header's column list like `id, "Full Name"`, as they appear in it.
+pub(crate) fn column_list(columns: &[u8]) -> Vec<&[u8]> {
+    let mut list = Vec::new();
+    let mut rest = columns;
+    loop {
+        let end = header::position_unquoted(rest, |b| b == b',').unwrap_or(rest.len());
+        list.push(header::trim_end(header::trim_start(&rest[..end])));
+        if end == rest.len() {
+            return list;
+        }
+        rest = &rest[end + 1..];
+    }
+}
+
+/// The positions in the column list of the columns named in `names`, the names compared without
+/// their quotes.
+pub(crate) fn positions(columns: &[&[u8]], names: &[&str], case_sensitive: bool) -> Vec<usize> {
+    columns
+        .iter()
+        .enumerate()
+        .filter(|(_, column)| {
+            let column = String::from_utf8_lossy(column);
+            let column = header::unquote(&column);
+            names.iter().any(|name| {
+                if case_sensitive {
+                    &*column == *name
+                } else {
+                    column.to_lowercase() == name.to_lowercase()
+                }
+            })
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// The header with the dropped columns left out of its column list.
+pub(crate) fn drop_header_columns(
+    buf: &[u8],
+    columns: &[u8],
+    list: &[&[u8]],
+    dropped: &[usize],
+) -> Vec<u8> {
+    // the column list is a part of the header line, it is replaced in place
+    let start = columns.as_ptr() as usize - buf.as_ptr() as usize;
+    let kept: Vec<&[u8]> = list
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !dropped.contains(i))
+        .map(|(_, column)| *column)
+        .collect();
+    let mut header = buf[..start].to_vec();
+    header.extend_from_slice(&kept.join(&b", "[..]));
+    header.extend_from_slice(&buf[start + columns.len()..]);
+    header
+}
+
+const MASK: &[u8] = b"***";
+
+/// What happens to the fields of the rows of a copy block, by their position.
+#[derive(Debug, Default)]
+pub(crate) struct ColumnEdits {
+    pub dropped: Vec<usize>,
+    pub masked: Vec<usize>,
+    /// Mask with a hash of the value keyed with this instead of `***`, so equal values stay equal
+    pub hash: Option<(u64, u64)>,
+    /// How a null is written in the rows, a masked null stays null
+    pub null: Vec<u8>,
+    /// Only the rows with one of the values, as written in the copy block, at each of these
+    /// positions are kept
+    pub conditions: Vec<(usize, Vec<Vec<u8>>)>,
+}
+
+impl ColumnEdits {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.dropped.is_empty() && self.masked.is_empty()
+    }
+
+    /// Whether the row has one of the values of every condition.
+    pub(crate) fn keeps(&self, line: &[u8]) -> bool {
+        if self.conditions.is_empty() {
+            return true;
+        }
+        let fields: Vec<&[u8]> = crate::trim_newline(line).split(|&b| b == b'\t').collect();
+        self.conditions.iter().all(|(i, values)| {
+            fields
+                .get(*i)
+                .is_some_and(|field| values.iter().any(|value| value == field))
+        })
+    }
+
+    /// The row with the dropped fields left out and the masked ones replaced, nulls stay null.
+    /// The fields of a row in text format are separated by tabs, a tab in a value is escaped as
+    /// `\t` so every tab separates.
+    pub(crate) fn edit_row(&self, line: &[u8]) -> Vec<u8> {
+        let row = crate::trim_newline(line);
+        let mut edited = Vec::with_capacity(line.len());
+        let mut first = true;
+        for (i, field) in row.split(|&b| b == b'\t').enumerate() {
+            if self.dropped.contains(&i) {
+                continue;
+            }
+            if !first {
+                edited.push(b'\t');
+            }
+            first = false;
+            if self.masked.contains(&i) && field != self.null {
+                if let Some(key) = self.hash {
+                    edited.extend_from_slice(format!("{:016x}", siphash(key, field)).as_bytes());
+                } else {
+                    edited.extend_from_slice(MASK);
+                }
+            } else {
+                edited.extend_from_slice(field);
+            }
+        }
+        edited.extend_from_slice(&line[row.len()..]);
+        edited
+    }
+}
+
+/// The value as it is written in a copy block in text format, with its backslashes, tabs and line
+/// endings escaped.  The `null` string stays the null it stands for.
+pub(crate) fn copy_text(value: &str, null: &str) -> Vec<u8> {
+    if value == null {
+        return null.as_bytes().to_vec();
+    }
+    let mut text = Vec::with_capacity(value.len());
+    for &b in value.as_bytes() {
+        match b {
+            b'\\' => text.extend_from_slice(b"\\\\"),
+            b'\t' => text.extend_from_slice(b"\\t"),
+            b'\n' => text.extend_from_slice(b"\\n"),
+            b'\r' => text.extend_from_slice(b"\\r"),
+            b => text.push(b),
+        }
+    }
+    text
+}
+
+/// Checks the row of a copy block in text format has `fields` fields, and only the escapes
+/// pg_dump writes: `\\`, `\b`, `\f`, `\n`, `\r`, `\t`, `\v`, octal and `\x` hex escapes,
+/// and `null`, e.g. `\N`, as a whole field.
+pub(crate) fn validate_row(line: &[u8], fields: usize, null: &[u8]) -> Result<(), String> {
+    let row = crate::trim_newline(line);
+    let mut count = 0;
+    for field in row.split(|&b| b == b'\t') {
+        count += 1;
+        if field == null {
+            continue;
+        }
+        let mut i = 0;
+        while i < field.len() {
+            if field[i] == b'\\' {
+                match field.get(i + 1) {
+                    Some(b'\\' | b'b' | b'f' | b'n' | b'r' | b't' | b'v' | b'0'..=b'7') => {}
+                    Some(b'x') if field.get(i + 2).is_some_and(u8::is_ascii_hexdigit) => {}
+                    Some(&b) => {
+                        return Err(format!(
+                            "malformed escape \\{} in field {}",
+                            char::from(b).escape_default(),
+                            count
+                        ))
+                    }
+                    None => return Err(format!("field {} ends in a backslash", count)),
+                }
+                i += 1;
+            }
+            i += 1;
+        }
+    }
+    if count != fields {
+        return Err(format!("row has {} fields, expected {}", count, fields));
+    }
+    Ok(())
+}
+
+/// The key of the hash masking the values derived from `mask_key`, a random one without it.
+pub(crate) fn mask_key(key: Option<&str>) -> (u64, u64) {
+    match key {
+        Some(key) => (
+            siphash((0, 0), key.as_bytes()),
+            siphash((0, 1), key.as_bytes()),
+        ),
+        None => {
+            // std seeds the keys of its hash maps from the operating system's randomness
+            let random = RandomState::new();
+            (random.hash_one(0u8), random.hash_one(1u8))
+        }
+    }
+}
+
+/// A random `mask_key`, for the filterings of the parts of a dump to hash alike.
+pub(crate) fn random_mask_key() -> String {
+    let (k0, k1) = mask_key(None);
+    format!("{:016x}{:016x}", k0, k1)
+}
+
+/// SipHash-2-4 of the bytes, a keyed hash that is the same on every platform and in every
+/// release.  Without the key the values can not be told from their hashes by hashing guesses.
+fn siphash((k0, k1): (u64, u64), bytes: &[u8]) -> u64 {
+    let mut v = [
+        k0 ^ 0x736f_6d65_7073_6575,
+        k1 ^ 0x646f_7261_6e64_6f6d,
+        k0 ^ 0x6c79_6765_6e65_7261,
+        k1 ^ 0x7465_6462_7974_6573,
+    ];
+    let round = |v: &mut [u64; 4]| {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    };
+    let compress = |v: &mut [u64; 4], m: u64| {
+        v[3] ^= m;
+        round(v);
+        round(v);
+        v[0] ^= m;
+    };
+    let mut chunks = bytes.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut m = [0; 8];
+        m.copy_from_slice(chunk);
+        compress(&mut v, u64::from_le_bytes(m));
+    }
+    // the last block has the rest of the bytes and the length in its top byte
+    let mut last = [0; 8];
+    last[..chunks.remainder().len()].copy_from_slice(chunks.remainder());
+    last[7] = bytes.len() as u8;
+    compress(&mut v, u64::from_le_bytes(last));
+    v[2] ^= 0xff;
+    for _ in 0..4 {
+        round(&mut v);
+    }
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
@@ -0,0 +1,60 @@
+/// Follows the dollar quotes (`$$` or `$tag$`) through a line of a statement, e.g. the body of a
+/// `CREATE FUNCTION ... AS $_$ ... $_$;`.  `open` is the tag of the quote the line starts in, the
+/// result the tag of the quote it ends in, if any.  Outside a dollar quote, single quoted strings
+/// and quoted identifiers are skipped so a `$` inside them does not open a quote.
+pub(crate) fn dollar_quote_after(line: &[u8], open: Option<Vec<u8>>) -> Option<Vec<u8>> {
+    let mut open = open;
+    let mut i = 0;
+    while i < line.len() {
+        match &open {
+            Some(tag) => match crate::find(&line[i..], tag) {
+                Some(start) => {
+                    i += start + tag.len();
+                    open = None;
+                }
+                None => return open,
+            },
+            None => match line[i] {
+                quote @ b'\'' | quote @ b'"' => {
+                    i += 1;
+                    while i < line.len() && line[i] != quote {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                b'$' if i == 0 || !is_identifier_byte(line[i - 1]) => match tag_len(&line[i..]) {
+                    Some(len) => {
+                        open = Some(line[i..i + len].to_vec());
+                        i += len;
+                    }
+                    None => i += 1,
+                },
+                _ => i += 1,
+            },
+        }
+    }
+    open
+}
+
+/// Length of the `$tag$` at the start of `rest`, if it starts with one.
+fn tag_len(rest: &[u8]) -> Option<usize> {
+    let end = rest[1..].iter().position(|&b| b == b'$')? + 1;
+    let tag = &rest[1..end];
+    let valid = match tag.first() {
+        None => true,
+        Some(first) => {
+            !first.is_ascii_digit() && tag.iter().all(|&b| b.is_ascii_alphanumeric() || b == b'_')
+        }
+    };
+    if valid {
+        Some(end + 1)
+    } else {
+        None
+    }
+}
+
+/// Whether the byte can be part of an unquoted identifier, a `$` right after one does not open a
+/// quote.
+fn is_identifier_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b >= 0x80
+}
@@ -0,0 +1,158 @@
+//! Parsing of the `COPY ... FROM stdin;` lines that start a copy block, and the
+//! `COPY ... TO stdout;` ones other tools than pg_dump write the same way.
+
+use std::borrow::Cow;
+
+const COPY: &[u8] = b"COPY ";
+const FROM_STDIN: &[u8] = b"FROM stdin";
+const TO_STDOUT: &[u8] = b"TO stdout";
+
+/// The parts of a copy block header like `COPY public.foo (a, b) FROM stdin WITH (FORMAT text);`.
+#[derive(Debug, PartialEq)]
+pub(crate) struct CopyHeader<'a> {
+    /// Table name as it appears in the header, e.g. `public."user"`
+    pub name: &'a [u8],
+    /// Column list without the parentheses, e.g. `a, b`
+    pub columns: Option<&'a [u8]>,
+    /// Whatever follows `FROM stdin` or `TO stdout` up to the `;`, e.g. `WITH (FORMAT text)`
+    pub options: &'a [u8],
+}
+
+impl<'a> CopyHeader<'a> {
+    /// Parses the line as a copy block header, `None` if it isn't one.
+    pub fn parse(buf: &'a [u8]) -> Option<CopyHeader<'a>> {
+        if !buf.starts_with(COPY) {
+            return None;
+        }
+        let line = trim_end(buf).strip_suffix(b";")?;
+        let rest = &line[COPY.len()..];
+        let name_end =
+            position_unquoted(rest, |b| b.is_ascii_whitespace() || b == b'(').unwrap_or(rest.len());
+        let (name, mut rest) = rest.split_at(name_end);
+        if name.is_empty() {
+            return None;
+        }
+        rest = trim_start(rest);
+        let mut columns = None;
+        if rest.starts_with(b"(") {
+            let columns_end = position_unquoted(rest, |b| b == b')')?;
+            columns = Some(&rest[1..columns_end]);
+            rest = trim_start(&rest[columns_end + 1..]);
+        }
+        let options = rest
+            .strip_prefix(FROM_STDIN)
+            .or_else(|| rest.strip_prefix(TO_STDOUT))?;
+        if !options.is_empty() && !options[0].is_ascii_whitespace() {
+            return None;
+        }
+        Some(CopyHeader {
+            name,
+            columns,
+            options: trim_start(options),
+        })
+    }
+}
+
+impl CopyHeader<'_> {
+    /// Whether the data is in binary format, i.e. `WITH (FORMAT binary)` or the older `BINARY`
+    /// option.
+    pub fn is_binary(&self) -> bool {
+        self.options
+            .split(|b| !b.is_ascii_alphanumeric())
+            .any(|word| word.eq_ignore_ascii_case(b"binary"))
+    }
+}
+
+/// Splits a table name as it appears in a header into its schema and table, without the double
+/// quotes around them, e.g. `"My Schema"."say ""hi"""` into `My Schema` and `say "hi"`.
+pub(crate) fn split_qualified_name(name: &str) -> Option<(Cow<'_, str>, Cow<'_, str>)> {
+    let (schema, rest) = identifier(name)?;
+    let (table, rest) = identifier(rest.strip_prefix('.')?)?;
+    if rest.is_empty() {
+        Some((schema, table))
+    } else {
+        None
+    }
+}
+
+/// Splits a dotted name like `public."user".id` into its identifiers, without their quotes.
+pub(crate) fn split_identifiers(name: &str) -> Option<Vec<Cow<'_, str>>> {
+    let mut identifiers = Vec::new();
+    let mut rest = name;
+    loop {
+        let (identifier, after) = identifier(rest)?;
+        identifiers.push(identifier);
+        match after.strip_prefix('.') {
+            Some(after) => rest = after,
+            None if after.is_empty() => return Some(identifiers),
+            None => return None,
+        }
+    }
+}
+
+/// The name without its double quotes if it is a quoted identifier, e.g. `user` for `"user"`.
+pub(crate) fn unquote(name: &str) -> Cow<'_, str> {
+    match identifier(name) {
+        Some((identifier, "")) if name.starts_with('"') => identifier,
+        _ => Cow::Borrowed(name),
+    }
+}
+
+/// The identifier at the start of `name`, unquoted, and what follows it.
+fn identifier(name: &str) -> Option<(Cow<'_, str>, &str)> {
+    let mut rest = match name.strip_prefix('"') {
+        Some(quoted) => quoted,
+        None => {
+            let end = name.find(&['.', '"'][..]).unwrap_or(name.len());
+            return match end {
+                0 => None,
+                _ => Some((Cow::Borrowed(&name[..end]), &name[end..])),
+            };
+        }
+    };
+    let mut identifier = Cow::Borrowed("");
+    loop {
+        let end = rest.find('"')?;
+        match rest[end + 1..].strip_prefix('"') {
+            // a `""` stands for a `"` in the identifier
+            Some(after) => {
+                identifier.to_mut().push_str(&rest[..=end]);
+                rest = after;
+            }
+            None if identifier.is_empty() => {
+                return Some((Cow::Borrowed(&rest[..end]), &rest[end + 1..]));
+            }
+            None => {
+                identifier.to_mut().push_str(&rest[..end]);
+                return Some((identifier, &rest[end + 1..]));
+            }
+        }
+    }
+}
+
+/// Position of the first byte outside of double quotes for which `f` holds.
+pub(crate) fn position_unquoted(buf: &[u8], f: impl Fn(u8) -> bool) -> Option<usize> {
+    let mut quoted = false;
+    buf.iter().position(|&b| {
+        if b == b'"' {
+            quoted = !quoted;
+        }
+        !quoted && f(b)
+    })
+}
+
+pub(crate) fn trim_start(buf: &[u8]) -> &[u8] {
+    let start = buf
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(buf.len());
+    &buf[start..]
+}
+
+pub(crate) fn trim_end(buf: &[u8]) -> &[u8] {
+    let end = buf
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace())
+        .map_or(0, |end| end + 1);
+    &buf[..end]
+}
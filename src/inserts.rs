@@ -0,0 +1,100 @@
+//! Turning the rows of a copy block into `INSERT` statements.
+
+use crate::header::CopyHeader;
+
+/// The start of the `INSERT` statements for the rows of the copy block with this header, e.g.
+/// `INSERT INTO public.foo (a, b) VALUES ` for `COPY public.foo (a, b) FROM stdin;`.
+pub(crate) fn insert_prefix(header: &CopyHeader) -> Vec<u8> {
+    let mut prefix = b"INSERT INTO ".to_vec();
+    prefix.extend_from_slice(header.name);
+    if let Some(columns) = header.columns {
+        prefix.extend_from_slice(b" (");
+        prefix.extend_from_slice(columns);
+        prefix.push(b')');
+    }
+    prefix.extend_from_slice(b" VALUES ");
+    prefix
+}
+
+/// The values of a row in text format as a parenthesized list of SQL literals, the `null` string
+/// like `\N` becoming `NULL` and every other value a quoted string that postgres casts to the
+/// column type.
+pub(crate) fn values(line: &[u8], null: &[u8]) -> Vec<u8> {
+    let row = crate::trim_newline(line);
+    let mut values = Vec::with_capacity(row.len() + 16);
+    values.push(b'(');
+    for (i, field) in row.split(|&b| b == b'\t').enumerate() {
+        if i > 0 {
+            values.extend_from_slice(b", ");
+        }
+        if field == null {
+            values.extend_from_slice(b"NULL");
+        } else {
+            literal(field, &mut values);
+        }
+    }
+    values.push(b')');
+    values
+}
+
+/// Appends the field as a quoted SQL string, undoing the backslash escapes of the text format.
+fn literal(field: &[u8], out: &mut Vec<u8>) {
+    out.push(b'\'');
+    for b in unescape(field) {
+        if b == b'\'' {
+            out.push(b'\'');
+        }
+        out.push(b);
+    }
+    out.push(b'\'');
+}
+
+/// The value of a field in text format, with its backslash escapes undone.
+pub(crate) fn unescape(field: &[u8]) -> Vec<u8> {
+    let mut value = Vec::with_capacity(field.len());
+    let mut i = 0;
+    while i < field.len() {
+        let b = match field[i] {
+            b'\\' if i + 1 < field.len() => {
+                i += 1;
+                match field[i] {
+                    b'b' => 0x08,
+                    b'f' => 0x0c,
+                    b'n' => b'\n',
+                    b'r' => b'\r',
+                    b't' => b'\t',
+                    b'v' => 0x0b,
+                    b'0'..=b'7' => {
+                        let len = digits(&field[i..], 3, |b| (b'0'..=b'7').contains(&b));
+                        let value = number(&field[i..i + len], 8);
+                        i += len - 1;
+                        value
+                    }
+                    b'x' if digits(&field[i + 1..], 2, |b| b.is_ascii_hexdigit()) > 0 => {
+                        let len = digits(&field[i + 1..], 2, |b| b.is_ascii_hexdigit());
+                        let value = number(&field[i + 1..i + 1 + len], 16);
+                        i += len;
+                        value
+                    }
+                    // any other escaped character stands for itself, e.g. `\\`
+                    other => other,
+                }
+            }
+            other => other,
+        };
+        value.push(b);
+        i += 1;
+    }
+    value
+}
+
+/// Number of bytes, at most `max`, at the start of `buf` for which `f` holds.
+fn digits(buf: &[u8], max: usize, f: impl Fn(u8) -> bool) -> usize {
+    buf.iter().take(max).take_while(|&&b| f(b)).count()
+}
+
+fn number(digits: &[u8], radix: u32) -> u8 {
+    digits.iter().fold(0u32, |value, &b| {
+        value * radix + (b as char).to_digit(radix).unwrap_or(0)
+    }) as u8
+}
@@ -0,0 +1,52 @@
+//! Turning the rows of a copy block into JSON objects, one per line.
+
+use crate::columns;
+use crate::header::{self, CopyHeader};
+
+/// The keys of the objects for the rows of the copy block with this header: the columns of its
+/// column list without their quotes, or the positions of the fields from 1 without one.
+pub(crate) fn keys(header: &CopyHeader) -> Vec<String> {
+    header
+        .columns
+        .map(columns::column_list)
+        .unwrap_or_default()
+        .iter()
+        .map(|column| header::unquote(&String::from_utf8_lossy(column)).into_owned())
+        .collect()
+}
+
+/// The row in text format of the table as a JSON object on a line of its own, like
+/// `{"table":"public.foo","row":{"id":"1","name":null}}`.  In the row the `null` string like `\N`
+/// becomes `null` and every other value a string.  Bytes that are not UTF-8 are replaced.
+pub(crate) fn object(table: &str, keys: &[String], line: &[u8], null: &[u8]) -> Vec<u8> {
+    let row = crate::trim_newline(line);
+    let mut object = Vec::with_capacity(row.len() * 2 + table.len() + 32);
+    object.extend_from_slice(b"{\"table\":");
+    string(table, &mut object);
+    object.extend_from_slice(b",\"row\":{");
+    for (i, field) in row.split(|&b| b == b'\t').enumerate() {
+        if i > 0 {
+            object.push(b',');
+        }
+        let key = match keys.get(i) {
+            Some(key) => key.clone(),
+            None => (i + 1).to_string(),
+        };
+        string(&key, &mut object);
+        object.push(b':');
+        if field == null {
+            object.extend_from_slice(b"null");
+        } else {
+            string(
+                &String::from_utf8_lossy(&crate::inserts::unescape(field)),
+                &mut object,
+            );
+        }
+    }
+    object.extend_from_slice(b"}}\n");
+    object
+}
+
+fn string(value: &str, out: &mut Vec<u8>) {
+    serde_json::to_writer(out, value).expect("a string is written to memory");
+}
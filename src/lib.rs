@@ -0,0 +1,1897 @@
+//! Filtering of plain text postgres dumps, as produced by `pg_dump --format=plain`.
+//!
+//! The dump is processed line by line by a small state machine: every line moves the [`State`]
+//! along and the resulting state decides whether the line is written to the output.
+
+use std::borrow::Cow;
+use std::io::{self, BufRead, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use columns::ColumnEdits;
+use header::CopyHeader;
+use progress::Progress;
+use serde::Serialize;
+use split::Split;
+use toc::{Owner, Section};
+use transform::Transform;
+
+#[macro_use]
+extern crate log;
+
+mod binary;
+mod columns;
+mod dollar;
+mod header;
+mod inserts;
+mod jsonl;
+mod lines;
+mod parts;
+mod prefix;
+mod progress;
+mod rename;
+mod split;
+mod stats;
+mod toc;
+mod transform;
+mod version;
+
+pub use lines::FilteredLines;
+pub use parts::filter_parts;
+/// How lines are told apart by their start, public for the benchmarks only.
+#[doc(hidden)]
+pub use prefix::{prefix as line_prefix, Prefix};
+pub use stats::{CopyBlockStats, Count, ManifestEntry, Reason, Stats};
+pub use version::Version;
+
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Whether the error is the output closed by its reader before the end, like `head` does once it
+/// has its lines.  Such an error is passed on as is, without the line it happened at.
+pub fn is_broken_pipe(error: &Error) -> bool {
+    error
+        .downcast_ref::<io::Error>()
+        .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+}
+
+/// Configuration of the filter, independent of how it was obtained (command line, code, ...).
+#[derive(Debug, Clone)]
+pub struct FilterConfig {
+    /// Copy blocks to drop, see [`FilterConfig::regex`] for how they are matched
+    pub excluded_copy_blocks: Vec<String>,
+    /// Copy blocks to keep, all others are dropped.  Keeps everything when empty
+    pub included_copy_blocks: Vec<String>,
+    /// Drop large object operations (lo_read, lowrite, lo_open, ...)
+    pub exclude_large_objects: bool,
+    /// Treat the copy blocks as regular expressions instead of exact names or globs
+    pub regex: bool,
+    /// Schemas of the objects, a copy block matches if it is in any of them
+    pub schemas: Vec<String>,
+    /// Initial size of the line buffer in MB, it grows to fit longer lines
+    pub buffersize_in_mb: usize,
+    /// Schemas to rename in the statements and copy block headers, as `(old, new)` pairs
+    pub rename_schemas: Vec<(String, String)>,
+    /// Tables to rename in the statements and copy block headers, as `(schema.table, new)` pairs
+    /// where the table keeps its schema
+    pub rename_tables: Vec<(String, String)>,
+    /// Fold the schema qualified and the quoted names in the statements and copy block headers
+    /// to lowercase, after the renames
+    pub lowercase_identifiers: bool,
+    /// Maximum number of rows to keep per copy block, an error for an included binary copy block
+    pub max_rows: Option<u64>,
+    /// Maximum number of copy blocks to keep, the ones after it are excluded whatever their name
+    pub max_blocks: Option<u64>,
+    /// Fraction (between 0 and 1) of the rows to keep per copy block, picked at random, an error
+    /// for an included binary copy block
+    pub sample: Option<f64>,
+    /// Seed for the random sampling of rows, makes the sample reproducible
+    pub seed: Option<u64>,
+    /// Keep the comments instead of dropping them
+    pub keep_comments: bool,
+    /// Keep the `-- PostgreSQL database dump complete` comment pg_dump ends a dump with, even
+    /// when the other comments are dropped or with `data_only`
+    pub keep_completion_marker: bool,
+    /// Start the output with the UTF-8 byte order mark the dump starts with, if it does, instead
+    /// of dropping it.  JSON lines have no byte order mark
+    pub keep_bom: bool,
+    /// The lines starting with any of these are comments, `--` for a dump of pg_dump
+    pub comment_prefixes: Vec<String>,
+    /// Keep runs of empty lines instead of collapsing them into one
+    pub keep_blank_lines: bool,
+    /// Drop the `SET ...` statements, except the ones setting the search path
+    pub strip_set: bool,
+    /// Drop the statements setting the search path
+    pub strip_search_path: bool,
+    /// Drop the `GRANT ...` and `REVOKE ...` statements
+    pub strip_grants: bool,
+    /// Drop the `ALTER TABLE ... OWNER TO ...` and `ALTER SEQUENCE ... OWNER TO ...` statements
+    pub strip_owner: bool,
+    /// Drop the statements starting with any of these, e.g. `CREATE INDEX`, ignoring case
+    pub excluded_statements: Vec<String>,
+    /// Drop the statements starting with none of these, ignoring case, unless empty.  The copy
+    /// blocks are not statements, they are included or excluded by their table
+    pub only_statements: Vec<String>,
+    /// Keep the `SELECT pg_catalog.setval(...)` calls setting the sequences, even with
+    /// `schema_only`.  They go with the data otherwise
+    pub keep_setval: bool,
+    /// Drop the `SELECT pg_catalog.setval(...)` calls, even with `data_only`
+    pub drop_setval: bool,
+    /// Drop all copy blocks, keeping only the schema
+    pub schema_only: bool,
+    /// Drop the ddl (`CREATE`, `ALTER`, `SET`, ...), comments and empty lines, keeping the data
+    pub data_only: bool,
+    /// With `schema_only` or `data_only`, tell the schema from the data by the `Type:` in the
+    /// table of contents comment of pg_dump before every object, rather than by how the lines
+    /// start.  A `-- Name: foo; Type: TABLE; ...` entry starts schema, a
+    /// `-- Data for Name: foo; Type: TABLE DATA; ...` one data
+    pub use_toc: bool,
+    /// Drop the statements, like `CREATE TABLE` and its indexes and constraints, of the tables
+    /// whose copy blocks are excluded or not included, telling the table of a statement by the
+    /// table of contents comment before it.  A foreign key of a kept table stays, even when it
+    /// references a dropped one
+    pub tables_only_matching_data: bool,
+    /// Fail when the dump ends inside a copy block
+    pub strict: bool,
+    /// Fail on a row of an included copy block in text format with another number of fields than
+    /// the columns of its header, or with an escape pg_dump does not write
+    pub validate_rows: bool,
+    /// Keep the large objects but write no data into them
+    pub truncate_large_objects: bool,
+    /// Oids of the large objects to drop, including their creation and the statements like
+    /// `ALTER LARGE OBJECT 16397 OWNER TO ...` about them
+    pub excluded_large_objects: Vec<u32>,
+    /// Fail on a call to a large object function that pg_dump does not write, rather than
+    /// treating it like the others
+    pub strict_large_objects: bool,
+    /// Report the number of bytes processed on stderr
+    pub progress: bool,
+    /// Write the included copy blocks to a file per table in this directory, and the rest of the
+    /// included lines to `schema.sql` in it, instead of to the writer
+    pub split_dir: Option<PathBuf>,
+    /// Stream the data rows longer than this many bytes to the output in chunks instead of reading
+    /// them into memory whole.  A streamed row is copied as it is, so this can not be combined
+    /// with the options editing the fields of the rows
+    pub stream_rows_over: Option<usize>,
+    /// Match the included/excluded copy blocks and the schemas case sensitively
+    pub case_sensitive: bool,
+    /// Match the included/excluded copy blocks that are plain names, no globs or regular
+    /// expressions, on the start of the table name, e.g. `events` matches the partitions
+    /// `events_2023` and `events_2024` but also `events_archive`
+    pub prefix_match: bool,
+    /// Match the copy blocks of tables without a schema, like `COPY foo FROM stdin;`, on their
+    /// table name alone
+    pub allow_unqualified: bool,
+    /// Swap the included and excluded copy blocks
+    pub invert: bool,
+    /// Fail unless the `-- Dumped from database version` comment is at least this version
+    pub min_version: Option<Version>,
+    /// Columns to leave out of the copy blocks, as `schema.table.column`
+    pub drop_columns: Vec<String>,
+    /// Columns to mask the values of in the copy blocks, as `schema.table.column`
+    pub mask_columns: Vec<String>,
+    /// Mask with a hash of the value instead of `***`
+    pub mask_hash: bool,
+    /// Key of the hash of `mask_hash`, the same key giving the same hashes, e.g. to join the
+    /// masked dumps of several databases.  A random key for every filtering when `None`
+    pub mask_key: Option<String>,
+    /// Keep only the rows with a value in a column, as `schema.table.column=value`, the
+    /// `null_string` for null.  The conditions on a table must all hold
+    pub where_equals: Vec<String>,
+    /// Keep only the rows with one of the values in a column, as
+    /// `schema.table.column=value,value,...`
+    pub where_in: Vec<String>,
+    /// How a null is written in the rows of the copy blocks, `\N` unless the dump was made with
+    /// another `NULL` option.  Masking keeps it, `to_inserts` and `to_jsonl` turn it into a null,
+    /// and a `where` value equal to it stands for null
+    pub null_string: String,
+    /// Write an `INSERT` statement per row instead of the copy blocks
+    pub to_inserts: bool,
+    /// Write the rows of the included copy blocks as JSON objects, one per line, and nothing else
+    /// of the dump.  An object has the `table` and the `row` keyed by its columns
+    pub to_jsonl: bool,
+    /// Maximum number of rows per `INSERT` statement
+    pub insert_batch: usize,
+    /// Statement to write before every included copy block, `%s` standing for its table name,
+    /// e.g. `TRUNCATE %s;`
+    pub before_block: Option<String>,
+    /// Statement to write after every included copy block, `%s` standing for its table name,
+    /// e.g. `ANALYZE %s;`
+    pub after_block: Option<String>,
+    /// Tables to pipe the rows of through a shell command, `sh -c` or `cmd /C` on Windows, its
+    /// output takes their place in the copy block, as `(table, command)` pairs
+    pub transforms: Vec<(String, String)>,
+    /// Tables to keep the copy blocks of without any rows, as `schema.table` or a table in
+    /// `schemas`.  Unlike an excluded copy block, the header and the `\.` stay, and a truncated
+    /// table counts as included when only some copy blocks are.  A binary copy block keeps its
+    /// signature, header and trailer
+    pub truncated_tables: Vec<String>,
+    /// Fail on a line, line ending included, longer than this many bytes instead of reading it
+    pub max_line_bytes: Option<usize>,
+}
+
+impl Default for FilterConfig {
+    fn default() -> FilterConfig {
+        FilterConfig {
+            excluded_copy_blocks: vec![],
+            included_copy_blocks: vec![],
+            exclude_large_objects: false,
+            regex: false,
+            schemas: vec![String::from("public")],
+            buffersize_in_mb: 32,
+            rename_schemas: vec![],
+            rename_tables: vec![],
+            lowercase_identifiers: false,
+            max_rows: None,
+            max_blocks: None,
+            sample: None,
+            seed: None,
+            keep_comments: false,
+            keep_completion_marker: false,
+            keep_bom: false,
+            comment_prefixes: vec![String::from("--")],
+            keep_blank_lines: false,
+            strip_set: false,
+            strip_search_path: false,
+            strip_grants: false,
+            strip_owner: false,
+            excluded_statements: vec![],
+            only_statements: vec![],
+            keep_setval: false,
+            drop_setval: false,
+            schema_only: false,
+            data_only: false,
+            use_toc: false,
+            tables_only_matching_data: false,
+            strict: false,
+            validate_rows: false,
+            truncate_large_objects: false,
+            excluded_large_objects: vec![],
+            strict_large_objects: false,
+            progress: false,
+            split_dir: None,
+            stream_rows_over: None,
+            case_sensitive: false,
+            prefix_match: false,
+            allow_unqualified: false,
+            invert: false,
+            min_version: None,
+            drop_columns: vec![],
+            mask_columns: vec![],
+            mask_hash: false,
+            mask_key: None,
+            where_equals: vec![],
+            where_in: vec![],
+            null_string: String::from("\\N"),
+            to_inserts: false,
+            to_jsonl: false,
+            insert_batch: 1,
+            before_block: None,
+            after_block: None,
+            transforms: vec![],
+            truncated_tables: vec![],
+            max_line_bytes: None,
+        }
+    }
+}
+
+impl FilterConfig {
+    /// Whether any of the options changes the included lines or adds lines of its own.  Without
+    /// one the output is made of lines of the dump, byte for byte and in the same order: nothing
+    /// is normalized, not even the line endings or the encoding.
+    pub fn rewrites_lines(&self) -> bool {
+        !self.drop_columns.is_empty()
+            || !self.mask_columns.is_empty()
+            || !self.rename_schemas.is_empty()
+            || !self.rename_tables.is_empty()
+            || self.lowercase_identifiers
+            || self.truncate_large_objects
+            || self.to_inserts
+            || self.to_jsonl
+            || self.before_block.is_some()
+            || self.after_block.is_some()
+            || !self.transforms.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Copy, Serialize)]
+pub enum State {
+    Init,
+    Comment,
+    /// The `-- PostgreSQL database dump complete` comment at the end, with
+    /// `keep_completion_marker`
+    CompletionMarker,
+    EmptyLine,
+    ConsecutiveEmptyLine,
+    IncludedCopyBlock,
+    /// A row of an included copy block left out, by `max_rows`, `sample`, a `where` condition or
+    /// `truncated_tables`.
+    /// The header and the `\.` of the block stay, so a block without any rows left is still a
+    /// valid empty copy
+    ExcludedRow,
+    EndOfIncludedCopyBlock,
+    ExcludedCopyBlock,
+    EndOfExcludedCopyBlock,
+    LargeObjectCreate,
+    LargeObject,
+    LargeObjectWrite,
+    ExcludedLargeObject,
+    Set,
+    SearchPath,
+    Grant,
+    Owner,
+    SetVal,
+    Ddl,
+    UnterminatedDdl,
+    DollarQuoted,
+    Statement,
+    ExcludedStatement,
+}
+
+const END_OF_COPY_BLOCK: &[u8] = b"\\.";
+const DUMP_COMPLETE: &[u8] = b"-- PostgreSQL database dump complete";
+const NEWLINE: &[u8] = b"\n";
+/// The UTF-8 byte order mark some editors start a file with.
+const BOM: &[u8] = b"\xef\xbb\xbf";
+const CRLF: &[u8] = b"\r\n";
+const COPY_BLOCK_PREFIX: &[u8] = b"COPY ";
+const LO_CREATE: &[u8] = b"SELECT pg_catalog.lo_create";
+const LO_FN: &[u8] = b"SELECT pg_catalog.lo_";
+const LO_OPEN: &[u8] = b"SELECT pg_catalog.lo_open";
+const LO_WRITE: &[u8] = b"SELECT pg_catalog.lowrite";
+const SET: &[u8] = b"SET ";
+const SET_SEARCH_PATH: &[u8] = b"SET search_path ";
+const SET_CONFIG_SEARCH_PATH: &[u8] = b"SELECT pg_catalog.set_config('search_path',";
+const GRANT: &[u8] = b"GRANT ";
+const REVOKE: &[u8] = b"REVOKE ";
+const ALTER_TABLE: &[u8] = b"ALTER TABLE ";
+const ALTER_SEQUENCE: &[u8] = b"ALTER SEQUENCE ";
+const OWNER_TO: &[u8] = b" OWNER TO ";
+const SETVAL: &[u8] = b"SELECT pg_catalog.setval(";
+/// Lower bound of [`FilterConfig::stream_rows_over`], so a `\.` line is always read whole.
+const MIN_STREAM_ROWS_OVER: usize = 16;
+/// The large object functions called in the dumps of pg_dump.
+const LARGE_OBJECT_FUNCTIONS: [&str; 6] = [
+    "lo_create",
+    "lo_open",
+    "lowrite",
+    "lo_close",
+    "lo_unlink",
+    "lo_from_bytea",
+];
+const DDL: [&[u8]; 4] = [b"CREATE ", b"ALTER ", b"DROP ", b"COMMENT ON "];
+
+/// Whether the bytes are the same, `==` on slices is not `const`.
+const fn bytes_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+// the prefixes are matched byte for byte, a lost trailing space would match other statements
+const _: () = {
+    assert!(bytes_eq(END_OF_COPY_BLOCK, b"\\."));
+    assert!(bytes_eq(
+        DUMP_COMPLETE,
+        b"-- PostgreSQL database dump complete"
+    ));
+    assert!(bytes_eq(NEWLINE, b"\n"));
+    assert!(bytes_eq(BOM, &[0xef, 0xbb, 0xbf]));
+    assert!(bytes_eq(CRLF, b"\r\n"));
+    assert!(bytes_eq(COPY_BLOCK_PREFIX, b"COPY "));
+    assert!(bytes_eq(LO_CREATE, b"SELECT pg_catalog.lo_create"));
+    assert!(bytes_eq(LO_FN, b"SELECT pg_catalog.lo_"));
+    assert!(bytes_eq(LO_OPEN, b"SELECT pg_catalog.lo_open"));
+    assert!(bytes_eq(LO_WRITE, b"SELECT pg_catalog.lowrite"));
+    assert!(bytes_eq(SET, b"SET "));
+    assert!(bytes_eq(SET_SEARCH_PATH, b"SET search_path "));
+    assert!(bytes_eq(
+        SET_CONFIG_SEARCH_PATH,
+        b"SELECT pg_catalog.set_config('search_path',"
+    ));
+    assert!(bytes_eq(GRANT, b"GRANT "));
+    assert!(bytes_eq(REVOKE, b"REVOKE "));
+    assert!(bytes_eq(ALTER_TABLE, b"ALTER TABLE "));
+    assert!(bytes_eq(ALTER_SEQUENCE, b"ALTER SEQUENCE "));
+    assert!(bytes_eq(OWNER_TO, b" OWNER TO "));
+    assert!(bytes_eq(SETVAL, b"SELECT pg_catalog.setval("));
+    assert!(bytes_eq(DDL[0], b"CREATE "));
+    assert!(bytes_eq(DDL[1], b"ALTER "));
+    assert!(bytes_eq(DDL[2], b"DROP "));
+    assert!(bytes_eq(DDL[3], b"COMMENT ON "));
+};
+
+/// Whether the line ends a copy block, i.e. is exactly `\.`.  A data row starting with `\.`
+/// does not, pg_dump writes the terminator on a line of its own.
+fn is_end_of_copy_block(buf: &[u8]) -> bool {
+    trim_newline(buf) == END_OF_COPY_BLOCK
+}
+
+/// Whether the line ends a statement, i.e. ends with `;`.
+fn is_end_of_statement(buf: &[u8]) -> bool {
+    trim_newline(buf).ends_with(b";")
+}
+
+/// Why the copy block of the table is included or excluded.
+fn copy_block_reason(name: &str, config: &FilterConfig, patterns: &CopyBlockPatterns) -> Reason {
+    match name {
+        _ if config.schema_only => Reason::SchemaOnly,
+        name if is_large_object_table(name, config) => Reason::LargeObjectData,
+        name if patterns.excluded.iter().any(|p| p.matches(name)) => Reason::Excluded,
+        name if !patterns.included.is_empty() && !patterns.is_included(name) => Reason::NotIncluded,
+        _ => Reason::Included,
+    }
+}
+
+/// Whether the statements of the table are kept with `tables_only_matching_data`, i.e. its copy
+/// block is not excluded and included if only some are.
+fn is_table_kept(name: &str, patterns: &CopyBlockPatterns) -> bool {
+    !patterns.excluded.iter().any(|p| p.matches(name))
+        && (patterns.included.is_empty() || patterns.is_included(name))
+}
+
+/// Whether the statement starts with one of the excluded statements or with none of the only
+/// statements, ignoring case.
+fn is_excluded_statement(buf: &[u8], config: &FilterConfig) -> bool {
+    let starts_with = |statement: &String| {
+        buf.get(..statement.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(statement.as_bytes()))
+    };
+    config.excluded_statements.iter().any(starts_with)
+        || (!config.only_statements.is_empty() && !config.only_statements.iter().any(starts_with))
+        || statement_large_object_oid(buf)
+            .is_some_and(|oid| config.excluded_large_objects.contains(&oid))
+}
+
+/// The oid of the large object an `ALTER LARGE OBJECT 16397 OWNER TO ...`,
+/// `COMMENT ON LARGE OBJECT 16397 IS ...` or `GRANT ... ON LARGE OBJECT 16397 TO ...` is about.
+fn statement_large_object_oid(buf: &[u8]) -> Option<u32> {
+    const LARGE_OBJECT: &[u8] = b" LARGE OBJECT ";
+    let start = find(buf, LARGE_OBJECT)? + LARGE_OBJECT.len();
+    let digits = &buf[start..];
+    let end = digits
+        .iter()
+        .position(|b| !b.is_ascii_digit())
+        .unwrap_or(digits.len());
+    std::str::from_utf8(&digits[..end]).ok()?.parse().ok()
+}
+
+/// Reads the lines after the first one of a statement into `buf`, up to the one ending with `;`.
+fn read_rest_of_statement<R: BufRead>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+    max_line_bytes: Option<usize>,
+) -> Result<usize> {
+    let mut read = 0;
+    while !is_end_of_statement(buf) {
+        match reader.read_until(b'\n', buf)? {
+            0 => break,
+            n => read += n,
+        }
+        if let Some(max) = max_line_bytes.filter(|&max| buf.len() > max) {
+            return Err(format!("a statement is longer than the maximum of {} bytes", max).into());
+        }
+    }
+    Ok(read)
+}
+
+/// The position of the first `needle` in `haystack`, if it is in it.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// The line without its `\n` or `\r\n` line ending.
+fn trim_newline(buf: &[u8]) -> &[u8] {
+    let buf = buf.strip_suffix(b"\n").unwrap_or(buf);
+    buf.strip_suffix(b"\r").unwrap_or(buf)
+}
+
+/// Whether the copy block loads the large objects into the catalog tables directly, and the large
+/// objects are to be excluded.  Truncated large objects keep their metadata, not their data.
+fn is_large_object_table(name: &str, config: &FilterConfig) -> bool {
+    match header::split_qualified_name(name) {
+        Some((schema, table)) if schema == "pg_catalog" => match &*table {
+            "pg_largeobject" => config.exclude_large_objects || config.truncate_large_objects,
+            "pg_largeobject_metadata" => config.exclude_large_objects,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// What happens to the columns of a copy block, and its header if columns are dropped from it.
+fn column_edits(
+    buf: &[u8],
+    header: &CopyHeader,
+    name: &str,
+    patterns: &CopyBlockPatterns,
+    config: &FilterConfig,
+) -> Result<(ColumnEdits, Option<Vec<u8>>)> {
+    let list = header.columns.map(columns::column_list).unwrap_or_default();
+    let positions = |columns: &[(CopyBlockPattern, String)]| {
+        let names: Vec<&str> = columns
+            .iter()
+            .filter(|(table, _)| table.matches(name))
+            .map(|(_, column)| column.as_str())
+            .collect();
+        columns::positions(&list, &names, config.case_sensitive)
+    };
+    let mut conditions = vec![];
+    for (table, column, values) in &patterns.where_columns {
+        if !table.matches(name) {
+            continue;
+        }
+        // keeping every row of the table when the column is missing would defeat the filter
+        match columns::positions(&list, &[column.as_str()], config.case_sensitive)[..] {
+            [i] => conditions.push((i, values.clone())),
+            _ => return Err(format!("no column {} in the copy block of {}", column, name).into()),
+        }
+    }
+    let edits = ColumnEdits {
+        dropped: positions(&patterns.dropped_columns),
+        masked: positions(&patterns.masked_columns),
+        hash: Some(patterns.mask_key).filter(|_| config.mask_hash),
+        null: config.null_string.as_bytes().to_vec(),
+        conditions,
+    };
+    if edits.is_empty() && edits.conditions.is_empty() {
+        return Ok((edits, None));
+    }
+    if header.is_binary() {
+        return Err(format!(
+            "can not edit the columns of the binary copy block of {}",
+            name
+        )
+        .into());
+    }
+    if edits.dropped.len() == list.len() {
+        return Err(format!("can not drop all the columns of {}", name).into());
+    }
+    let rewritten = match header.columns {
+        Some(columns) if !edits.dropped.is_empty() => Some(columns::drop_header_columns(
+            buf,
+            columns,
+            &list,
+            &edits.dropped,
+        )),
+        _ => None,
+    };
+    Ok((edits, rewritten))
+}
+
+/// Where an included line goes: the file of the split it belongs to or the writer.  The lines
+/// that are not included go nowhere.
+fn destination<'a, W: Write>(
+    split: &'a mut Option<Split>,
+    writer: &'a mut W,
+    sink: &'a mut io::Sink,
+    state: State,
+    included: bool,
+) -> Result<&'a mut dyn Write> {
+    Ok(match split {
+        Some(split) if included => split.writer(state)?,
+        _ if included => writer,
+        _ => sink,
+    })
+}
+
+/// The buffered input like `reader.fill_buf()`, retried when a signal interrupts the read as
+/// `read_until` and `write_all` do.
+pub fn fill_buf<R: BufRead + ?Sized>(reader: &mut R) -> io::Result<&[u8]> {
+    loop {
+        match reader.fill_buf() {
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+            Ok(_) => break,
+        }
+    }
+    reader.fill_buf()
+}
+
+/// Copies the rest of a line that did not fit in the buffer in chunks, up to and including its
+/// `\n`.  Returns the number of bytes copied.
+fn copy_rest_of_line<R: BufRead>(reader: &mut R, out: &mut dyn Write) -> Result<u64> {
+    let mut copied = 0;
+    loop {
+        let chunk = fill_buf(reader)?;
+        if chunk.is_empty() {
+            return Ok(copied);
+        }
+        let (len, is_end_of_line) = match chunk.iter().position(|&b| b == b'\n') {
+            Some(newline) => (newline + 1, true),
+            None => (chunk.len(), false),
+        };
+        out.write_all(&chunk[..len])?;
+        reader.consume(len);
+        copied += len as u64;
+        if is_end_of_line {
+            return Ok(copied);
+        }
+    }
+}
+
+/// The oid of the large object a `lo_create('16397')` or `lo_open('16397', 131072)` call is about,
+/// i.e. the first number in its arguments.
+fn large_object_oid(buf: &[u8]) -> Option<u32> {
+    let arguments = &buf[buf.iter().position(|&b| b == b'(')?..];
+    let start = arguments.iter().position(u8::is_ascii_digit)?;
+    let digits = &arguments[start..];
+    let end = digits
+        .iter()
+        .position(|b| !b.is_ascii_digit())
+        .unwrap_or(digits.len());
+    std::str::from_utf8(&digits[..end]).ok()?.parse().ok()
+}
+
+/// The function of a large object call, e.g. `lo_open` for `SELECT pg_catalog.lo_open(...)`.
+fn large_object_function(buf: &[u8]) -> String {
+    let name = buf.strip_prefix(b"SELECT pg_catalog.").unwrap_or(buf);
+    let end = name
+        .iter()
+        .position(|&b| !b.is_ascii_alphanumeric() && b != b'_')
+        .unwrap_or(name.len());
+    String::from_utf8_lossy(&name[..end]).into_owned()
+}
+
+/// The statement of a `before_block` or `after_block` hook for the table, on a line of its own.
+fn hook(template: &str, name: &[u8]) -> Vec<u8> {
+    // the name may not be UTF-8, it is put in as is
+    let mut statement = Vec::with_capacity(template.len() + name.len() + 1);
+    for (i, part) in template.split("%s").enumerate() {
+        if i > 0 {
+            statement.extend_from_slice(name);
+        }
+        statement.extend_from_slice(part.as_bytes());
+    }
+    statement.push(b'\n');
+    statement
+}
+
+/// Replaces the data written by a `SELECT pg_catalog.lowrite(0, '\\x...');` call with nothing,
+/// so the large object is still opened and written to but ends up empty.
+fn truncate_lowrite(buf: &[u8]) -> Vec<u8> {
+    match buf.iter().position(|&b| b == b',') {
+        Some(comma) => {
+            let line = trim_newline(buf);
+            let mut truncated = buf[..comma].to_vec();
+            truncated.extend_from_slice(b", '');");
+            truncated.extend_from_slice(&buf[line.len()..]);
+            truncated
+        }
+        None => buf.to_vec(),
+    }
+}
+
+/// The name of the table a copy block loads as it appears in the header, e.g. `public.foo` for
+/// `COPY public.foo (a, b) FROM stdin;` and `"My Schema"."user"` for
+/// `COPY "My Schema"."user" FROM stdin;`.  Bytes that are not UTF-8, as in a dump in another
+/// encoding, become `�` and never match a copy block given as UTF-8.
+fn copy_block_name<'a>(header: &CopyHeader<'a>) -> Cow<'a, str> {
+    String::from_utf8_lossy(header.name)
+}
+
+/// An included or excluded copy block as given on the command line.  Entries containing `*` or
+/// `?` are shell-style globs, all others must match the table name exactly.  With `--regex` every
+/// entry is a regular expression.  A copy block matches if it is in one of the schemas and its
+/// table name matches, ignoring case in all cases.  A table name without a schema only matches
+/// when unqualified names are allowed, whatever the schemas.
+struct CopyBlockPattern {
+    /// The entry as given, for reporting
+    block: String,
+    schemas: Vec<String>,
+    table: TablePattern,
+    case_sensitive: bool,
+    allow_unqualified: bool,
+}
+
+enum TablePattern {
+    Exact(String),
+    Prefix(String),
+    Glob(glob::Pattern),
+    Regex(regex::Regex),
+}
+
+impl CopyBlockPattern {
+    fn new(
+        schemas: &[String],
+        block: &str,
+        is_regex: bool,
+        case_sensitive: bool,
+        allow_unqualified: bool,
+    ) -> Result<CopyBlockPattern> {
+        let lowercase = |name: &str| {
+            if case_sensitive {
+                name.to_string()
+            } else {
+                name.to_lowercase()
+            }
+        };
+        // the names are compared without their quotes, `"user"` is the same as `user`
+        let unquoted = header::unquote(block);
+        let table = if is_regex {
+            let regex = regex::RegexBuilder::new(block)
+                .case_insensitive(!case_sensitive)
+                .build()
+                .map_err(|e| format!("invalid regex '{}': {}", block, e))?;
+            TablePattern::Regex(regex)
+        } else if unquoted.contains(&['*', '?'][..]) {
+            TablePattern::Glob(glob::Pattern::new(&unquoted)?)
+        } else {
+            TablePattern::Exact(lowercase(&unquoted))
+        };
+        Ok(CopyBlockPattern {
+            block: block.to_string(),
+            schemas: schemas
+                .iter()
+                .map(|schema| lowercase(&header::unquote(schema)))
+                .collect(),
+            table,
+            case_sensitive,
+            allow_unqualified,
+        })
+    }
+
+    /// The pattern matching the tables starting with its name, if it is a plain name.
+    fn matching_prefix(self) -> CopyBlockPattern {
+        let table = match self.table {
+            TablePattern::Exact(exact) => TablePattern::Prefix(exact),
+            table => table,
+        };
+        CopyBlockPattern { table, ..self }
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        let (schema, table) = match header::split_qualified_name(name) {
+            Some((schema, table)) => (Some(schema), table),
+            None if self.allow_unqualified => (None, header::unquote(name)),
+            None => return false,
+        };
+        let table = &*table;
+        let eq = |name: &str, pattern: &str| {
+            if self.case_sensitive {
+                name == pattern
+            } else {
+                eq_lowercase(name, pattern)
+            }
+        };
+        if let Some(schema) = schema {
+            if !self.schemas.iter().any(|s| eq(&schema, s)) {
+                return false;
+            }
+        }
+        match &self.table {
+            TablePattern::Exact(exact) => eq(table, exact),
+            TablePattern::Prefix(prefix) if self.case_sensitive => table.starts_with(prefix),
+            // the start of the name folded like an unquoted identifier, ASCII only
+            TablePattern::Prefix(prefix) => table
+                .as_bytes()
+                .get(..prefix.len())
+                .is_some_and(|start| start.eq_ignore_ascii_case(prefix.as_bytes())),
+            TablePattern::Glob(glob) => glob.matches_with(
+                table,
+                glob::MatchOptions {
+                    case_sensitive: self.case_sensitive,
+                    ..glob::MatchOptions::new()
+                },
+            ),
+            TablePattern::Regex(regex) => regex.is_match(table),
+        }
+    }
+}
+
+/// Whether the table of a copy block is in the schema, compared like the copy block patterns do.
+fn is_in_schema(name: &str, schema: &str, config: &FilterConfig) -> bool {
+    let schema = header::unquote(schema);
+    match header::split_qualified_name(name) {
+        Some((table_schema, _)) if config.case_sensitive => table_schema == schema,
+        Some((table_schema, _)) => eq_lowercase(&table_schema, &schema.to_lowercase()),
+        None => false,
+    }
+}
+
+/// Whether `name` equals `lowercase` ignoring case, without allocating a lowercase copy of it.
+fn eq_lowercase(name: &str, lowercase: &str) -> bool {
+    if name.is_ascii() && lowercase.is_ascii() {
+        return name.eq_ignore_ascii_case(lowercase);
+    }
+    name.chars()
+        .flat_map(char::to_lowercase)
+        .eq(lowercase.chars())
+}
+
+struct CopyBlockPatterns {
+    included: Vec<CopyBlockPattern>,
+    excluded: Vec<CopyBlockPattern>,
+    /// The tables to drop columns from, with the name of the column
+    dropped_columns: Vec<(CopyBlockPattern, String)>,
+    /// The tables to mask columns of, with the name of the column
+    masked_columns: Vec<(CopyBlockPattern, String)>,
+    /// The tables to keep the rows of with one of the values in a column, with the name of the
+    /// column and the values as written in the copy block
+    where_columns: Vec<(CopyBlockPattern, String, Vec<Vec<u8>>)>,
+    /// The tables to pipe the rows of through a command, with the command
+    transforms: Vec<(CopyBlockPattern, String)>,
+    /// The tables to keep the copy blocks of without their rows
+    truncated: Vec<CopyBlockPattern>,
+    /// The key of the hash masking the values with `mask_hash`
+    mask_key: (u64, u64),
+}
+
+impl CopyBlockPatterns {
+    /// Whether the copy block is in the included ones, a truncated table is included too.
+    fn is_included(&self, name: &str) -> bool {
+        self.included
+            .iter()
+            .chain(&self.truncated)
+            .any(|p| p.matches(name))
+    }
+
+    fn new(config: &FilterConfig) -> Result<CopyBlockPatterns> {
+        let compile = |blocks: &[String]| -> Result<Vec<CopyBlockPattern>> {
+            blocks
+                .iter()
+                .map(|block| {
+                    let pattern = CopyBlockPattern::new(
+                        &config.schemas,
+                        block,
+                        config.regex,
+                        config.case_sensitive,
+                        config.allow_unqualified,
+                    )?;
+                    Ok(if config.prefix_match {
+                        pattern.matching_prefix()
+                    } else {
+                        pattern
+                    })
+                })
+                .collect()
+        };
+        let (included, excluded) = if config.invert {
+            (&config.excluded_copy_blocks, &config.included_copy_blocks)
+        } else {
+            (&config.included_copy_blocks, &config.excluded_copy_blocks)
+        };
+        // with the lists from files both can be given, the same table in both is a mistake
+        let normalized = |block: &str| {
+            let unquoted = header::unquote(block);
+            if config.case_sensitive {
+                unquoted.into_owned()
+            } else {
+                unquoted.to_lowercase()
+            }
+        };
+        for block in included {
+            if excluded
+                .iter()
+                .any(|other| normalized(other) == normalized(block))
+            {
+                return Err(format!("copy block '{}' is both included and excluded", block).into());
+            }
+        }
+        let compile_columns = |columns: &[String]| -> Result<Vec<(CopyBlockPattern, String)>> {
+            columns
+                .iter()
+                .map(
+                    |column| match header::split_identifiers(column).as_deref() {
+                        Some([schema, table, name]) => Ok((
+                            CopyBlockPattern::new(
+                                &[schema.to_string()],
+                                table,
+                                false,
+                                config.case_sensitive,
+                                config.allow_unqualified,
+                            )?,
+                            name.to_string(),
+                        )),
+                        _ => Err(format!("expected schema.table.column, got '{}'", column).into()),
+                    },
+                )
+                .collect()
+        };
+        // a `where_in` condition has a list of values, a `where_equals` one a single value
+        let conditions = config
+            .where_equals
+            .iter()
+            .map(|c| (c, false))
+            .chain(config.where_in.iter().map(|c| (c, true)));
+        let mut where_columns = vec![];
+        for (condition, is_list) in conditions {
+            let (column, value) = condition.split_once('=').ok_or_else(|| {
+                format!("expected schema.table.column=value, got '{}'", condition)
+            })?;
+            let (table, name) = compile_columns(&[column.to_string()])?.remove(0);
+            let values = if is_list {
+                value
+                    .split(',')
+                    .map(|value| columns::copy_text(value, &config.null_string))
+                    .collect()
+            } else {
+                vec![columns::copy_text(value, &config.null_string)]
+            };
+            where_columns.push((table, name, values));
+        }
+        // a table is in its schema, or in the schemas of the config like a copy block
+        let table_pattern = |table: &str| -> Result<CopyBlockPattern> {
+            let (schemas, table) = match header::split_identifiers(table).as_deref() {
+                Some([schema, table]) => (vec![schema.to_string()], table.to_string()),
+                Some([table]) => (config.schemas.clone(), table.to_string()),
+                _ => return Err(format!("expected schema.table, got '{}'", table).into()),
+            };
+            CopyBlockPattern::new(
+                &schemas,
+                &table,
+                false,
+                config.case_sensitive,
+                config.allow_unqualified,
+            )
+        };
+        let mut transforms = vec![];
+        for (table, command) in &config.transforms {
+            transforms.push((table_pattern(table)?, command.clone()));
+        }
+        let truncated = config
+            .truncated_tables
+            .iter()
+            .map(|table| table_pattern(table))
+            .collect::<Result<_>>()?;
+        Ok(CopyBlockPatterns {
+            included: compile(included)?,
+            excluded: compile(excluded)?,
+            truncated,
+            dropped_columns: compile_columns(&config.drop_columns)?,
+            masked_columns: compile_columns(&config.mask_columns)?,
+            where_columns,
+            transforms,
+            mask_key: columns::mask_key(config.mask_key.as_deref()),
+        })
+    }
+}
+
+impl State {
+    fn next_state(
+        &self,
+        buf: &[u8],
+        config: &FilterConfig,
+        patterns: &CopyBlockPatterns,
+    ) -> Result<State> {
+        let state = match self {
+            // inside a copy block every line is data, no matter what it starts with
+            State::IncludedCopyBlock | State::ExcludedRow | State::ExcludedCopyBlock => {
+                self.next_copy_block_state(buf)
+            }
+            // a ddl statement can span several lines, up to the closing `;`
+            State::UnterminatedDdl if is_end_of_statement(buf) => State::Ddl,
+            State::UnterminatedDdl => State::UnterminatedDdl,
+            // until the loop sees the closing tag of the dollar quote
+            State::DollarQuoted => State::DollarQuoted,
+            _ => self.next_statement_state(buf, config, patterns)?,
+        };
+        if state != *self {
+            trace!(
+                "{:?} -> {:?}: {}",
+                self,
+                state,
+                String::from_utf8_lossy(trim_newline(buf))
+            );
+        }
+        Ok(state)
+    }
+
+    /// Whether the line is (a part of) a statement, rather than of a copy block, a comment or an
+    /// empty line.
+    fn is_statement(&self) -> bool {
+        matches!(
+            self,
+            State::LargeObjectCreate
+                | State::LargeObject
+                | State::LargeObjectWrite
+                | State::SearchPath
+                | State::Set
+                | State::Grant
+                | State::Owner
+                | State::SetVal
+                | State::Ddl
+                | State::UnterminatedDdl
+                | State::DollarQuoted
+                | State::Statement
+        )
+    }
+
+    /// Whether the next line starts a statement, rather than going on with a copy block, a ddl
+    /// statement or a dollar quote.
+    fn is_between_statements(&self) -> bool {
+        !self.is_copy_block() && !matches!(self, State::UnterminatedDdl | State::DollarQuoted)
+    }
+
+    /// Whether the state is the header or the data of a copy block.
+    fn is_copy_block(&self) -> bool {
+        matches!(
+            self,
+            State::IncludedCopyBlock | State::ExcludedRow | State::ExcludedCopyBlock
+        )
+    }
+
+    fn next_copy_block_state(&self, buf: &[u8]) -> State {
+        match self {
+            State::IncludedCopyBlock | State::ExcludedRow if is_end_of_copy_block(buf) => {
+                State::EndOfIncludedCopyBlock
+            }
+            // rows are excluded one at a time, the next row is up for inclusion again
+            State::ExcludedRow => State::IncludedCopyBlock,
+            State::ExcludedCopyBlock if is_end_of_copy_block(buf) => State::EndOfExcludedCopyBlock,
+            state => *state,
+        }
+    }
+
+    fn next_statement_state(
+        &self,
+        buf: &[u8],
+        config: &FilterConfig,
+        patterns: &CopyBlockPatterns,
+    ) -> Result<State> {
+        let is_comment = config
+            .comment_prefixes
+            .iter()
+            .any(|comment| buf.starts_with(comment.as_bytes()));
+        if is_comment {
+            if config.keep_completion_marker && trim_newline(buf) == DUMP_COMPLETE {
+                return Ok(State::CompletionMarker);
+            }
+            return Ok(State::Comment);
+        }
+        match prefix::prefix(buf) {
+            // keep the lo_create calls (oid colums in tables must work)
+            Some(Prefix::LargeObjectCreate) => Ok(State::LargeObjectCreate),
+            Some(Prefix::LargeObject) => Ok(State::LargeObject),
+            Some(Prefix::LargeObjectWrite) => Ok(State::LargeObjectWrite),
+            Some(Prefix::EmptyLine) => match self {
+                State::EmptyLine => Ok(State::ConsecutiveEmptyLine),
+                State::ConsecutiveEmptyLine => Ok(State::ConsecutiveEmptyLine),
+                _ => Ok(State::EmptyLine),
+            },
+            Some(Prefix::SearchPath) => Ok(State::SearchPath),
+            Some(Prefix::Set) => Ok(State::Set),
+            Some(Prefix::Grant) => Ok(State::Grant),
+            Some(Prefix::SetVal) => Ok(State::SetVal),
+            Some(Prefix::AlterTableOrSequence) if find(buf, OWNER_TO).is_some() => Ok(State::Owner),
+            Some(Prefix::EndOfCopyBlock) => Ok(*self),
+            Some(Prefix::CopyBlock) => match CopyHeader::parse(buf) {
+                Some(header) => Ok(State::copy_block_state(&header, config, patterns)),
+                None => Ok(State::Statement),
+            },
+            Some(Prefix::Ddl) | Some(Prefix::AlterTableOrSequence) => match buf {
+                buf if is_end_of_statement(buf) => Ok(State::Ddl),
+                _ => Ok(State::UnterminatedDdl),
+            },
+            None => Ok(State::Statement),
+        }
+    }
+
+    fn copy_block_state(
+        header: &CopyHeader,
+        config: &FilterConfig,
+        patterns: &CopyBlockPatterns,
+    ) -> State {
+        let name = copy_block_name(header);
+        let reason = copy_block_reason(&name, config, patterns);
+        let state = match reason {
+            Reason::Included => State::IncludedCopyBlock,
+            _ => State::ExcludedCopyBlock,
+        };
+        debug!("copy block {}: {:?}, {}", name, state, reason);
+        state
+    }
+
+    fn must_include(&self, config: &FilterConfig, prev_included_state: &State) -> bool {
+        let include = self.is_included(config, prev_included_state);
+        trace!(
+            "{:?}: {}",
+            self,
+            if include { "included" } else { "dropped" }
+        );
+        include
+    }
+
+    fn is_included(&self, config: &FilterConfig, prev_included_state: &State) -> bool {
+        match self {
+            // only the copy blocks and statements like setval or lo_* calls remain
+            State::Comment
+            | State::EmptyLine
+            | State::ConsecutiveEmptyLine
+            | State::Set
+            | State::SearchPath
+            | State::Grant
+            | State::Owner
+            | State::Ddl
+            | State::UnterminatedDdl
+            | State::DollarQuoted
+                if config.data_only =>
+            {
+                false
+            }
+            State::Comment if !config.keep_comments => false,
+            State::ConsecutiveEmptyLine if !config.keep_blank_lines => false,
+            State::ExcludedRow => false,
+            State::ExcludedCopyBlock => false,
+            State::EndOfExcludedCopyBlock => false,
+            State::LargeObject | State::LargeObjectWrite if config.exclude_large_objects => false,
+            State::ExcludedLargeObject => false,
+            State::ExcludedStatement => false,
+            // the sequences are set to where the data left them, by default they go with it
+            State::SetVal if config.drop_setval => false,
+            State::SetVal if config.keep_setval => true,
+            State::SetVal if config.schema_only => false,
+            State::Set if config.strip_set => false,
+            State::SearchPath if config.strip_search_path => false,
+            State::Grant if config.strip_grants => false,
+            State::Owner if config.strip_owner => false,
+            State::EmptyLine
+                if !config.keep_blank_lines && prev_included_state == &State::EmptyLine =>
+            {
+                false
+            }
+            _ => true,
+        }
+    }
+}
+
+/// The offset of the first copy block header starting at or after `offset`, or of the end of the
+/// dump if no copy block follows.  Offset 0 stays at the start of the dump.  The parts of a dump
+/// between the boundaries of a few offsets can be filtered apart and their outputs put together
+/// again, as a part always starts outside of a copy block.
+///
+/// A data row can read like a header, so only a header after the table of contents entry pg_dump
+/// writes before it, `--`, `-- Data for Name: ...`, `--` and an empty line, is a boundary.  A dump
+/// without these comments, e.g. one made with `--no-comments`, has no boundaries to tell and is an
+/// error.
+pub fn copy_block_boundary<R: BufRead + Seek>(reader: &mut R, offset: u64) -> Result<u64> {
+    if offset == 0 {
+        return Ok(0);
+    }
+    // the line the offset falls in is skipped, unless the offset is at its start
+    let mut line = Vec::new();
+    reader.seek(SeekFrom::Start(offset - 1))?;
+    let mut position = offset - 1 + reader.read_until(b'\n', &mut line)? as u64;
+    // how many lines of the table of contents entry before a header were read last, an entry
+    // before the offset is not seen
+    let mut entry_lines = 0;
+    let mut lines = 0;
+    let mut header_without_entry = None;
+    loop {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line)?;
+        if read == 0 {
+            return match header_without_entry {
+                Some(header) => Err(format!(
+                    "the copy block header at byte {} has no \"-- Data for Name:\" comment \
+                     before it, the copy block boundaries can not be told from the data",
+                    header
+                )
+                .into()),
+                None => Ok(position),
+            };
+        }
+        if line.starts_with(COPY_BLOCK_PREFIX) {
+            if entry_lines == 4 {
+                return Ok(position);
+            }
+            if lines >= 4 && CopyHeader::parse(&line).is_some() {
+                header_without_entry = header_without_entry.or(Some(position));
+            }
+        }
+        let line = trim_newline(&line);
+        entry_lines = match entry_lines {
+            1 if line.starts_with(toc::DATA_FOR_NAME) => 2,
+            2 if line == b"--" => 3,
+            3 if line.is_empty() => 4,
+            _ if line == b"--" => 1,
+            _ => 0,
+        };
+        lines += 1;
+        position += read as u64;
+    }
+}
+
+/// Filters the dump read from `reader` according to `config` and writes the result to `writer`.
+/// Returns how many lines and bytes were included or dropped.
+///
+/// The included lines are written as they are in the dump unless
+/// [`rewrites_lines`](FilterConfig::rewrites_lines) holds for the config.
+///
+/// Filtering is idempotent: filtering the output again with the same config gives the same output,
+/// as whatever is dropped or collapsed the first time is already gone.  The exceptions are the
+/// options that change the data itself every time: `sample`, `mask_hash` and renames chained
+/// into each other like `a=b` and `b=c`, the options adding to the output every time:
+/// `before_block` and `after_block`, and `to_jsonl` whose output is no dump.
+pub fn filter<R: BufRead, W: Write>(
+    reader: R,
+    mut writer: W,
+    config: &FilterConfig,
+) -> Result<Stats> {
+    let mut filter = Filter::new(reader, config)?;
+    while filter.next_line(&mut writer)? {}
+    writer.flush()?;
+    filter.finish()
+}
+
+/// The filter going through the dump a line at a time, with what it has to remember from one
+/// line to the next.
+pub(crate) struct Filter<'c, R> {
+    reader: R,
+    config: &'c FilterConfig,
+    patterns: CopyBlockPatterns,
+    prev_included_state: State,
+    state: State,
+    buf: Vec<u8>,
+    stats: Stats,
+    included_matched: Vec<bool>,
+    large_object: Option<u32>,
+    dollar_quote: Option<Vec<u8>>,
+    split: Option<Split>,
+    rng: fastrand::Rng,
+    sink: io::Sink,
+    version_checked: bool,
+    column_edits_of_block: ColumnEdits,
+    insert_prefix: Vec<u8>,
+    rows_in_insert: usize,
+    /// The table and the keys of the JSON objects for the rows of the included copy block with
+    /// `to_jsonl`
+    jsonl_table: String,
+    jsonl_keys: Vec<String>,
+    progress: Option<Progress>,
+    /// Table name of the included copy block being filtered, for the hook after it
+    block_name: Option<Vec<u8>>,
+    /// Number of fields of the rows of the copy block being filtered with `validate_rows`, that of
+    /// its first row when the header has no column list
+    row_fields: Option<usize>,
+    /// The command the rows of the included copy block being filtered are piped through
+    transform: Option<Transform>,
+    /// Whether the rows of the copy block being filtered are all left out by `truncated_tables`
+    truncated: bool,
+    /// The section of the last table of contents entry with `use_toc`
+    toc_section: Option<Section>,
+    /// Whether the statement being filtered is dropped by `excluded_statements` or
+    /// `only_statements`
+    statement_excluded: bool,
+    /// The table the statements after the last table of contents entry belong to with
+    /// `tables_only_matching_data`
+    toc_owner: Owner,
+    /// Number of the line of the dump being filtered, counting from 1
+    line_number: u64,
+    /// Number of lines of the dump read so far, the data of binary copy blocks aside
+    lines_read: u64,
+}
+
+impl<'c, R: BufRead> Filter<'c, R> {
+    pub(crate) fn new(reader: R, config: &'c FilterConfig) -> Result<Filter<'c, R>> {
+        let patterns = CopyBlockPatterns::new(config)?;
+        if config.comment_prefixes.iter().any(String::is_empty) {
+            return Err("a comment prefix can not be empty".into());
+        }
+        for (old, _) in &config.rename_tables {
+            if header::split_qualified_name(old).is_none() {
+                return Err(format!("can not rename '{}', expected schema.table", old).into());
+            }
+        }
+        // the output of a transform is written as it comes, it is not turned into inserts
+        if config.to_inserts && !config.transforms.is_empty() {
+            return Err("transforms and to_inserts can not be combined".into());
+        }
+        if config.to_jsonl
+            && (config.to_inserts
+                || !config.transforms.is_empty()
+                || config.stream_rows_over.is_some()
+                || config.before_block.is_some()
+                || config.after_block.is_some())
+        {
+            return Err("to_jsonl can not be combined with to_inserts, transforms, \
+                 stream_rows_over, before_block or after_block"
+                .into());
+        }
+        // the rest of a streamed row is copied as it is, the column edits only see its start
+        let edits_columns = !config.drop_columns.is_empty()
+            || !config.mask_columns.is_empty()
+            || !config.where_equals.is_empty()
+            || !config.where_in.is_empty()
+            || config.to_inserts;
+        if config.stream_rows_over.is_some() && edits_columns {
+            return Err(
+                "stream_rows_over can not be combined with drop_columns, mask_columns, \
+                        where_equals, where_in or to_inserts"
+                    .into(),
+            );
+        }
+        let included_matched = vec![false; patterns.included.len()];
+        let split = match &config.split_dir {
+            Some(dir) => Some(Split::new(dir)?),
+            None => None,
+        };
+        let rng = match config.seed {
+            Some(seed) => fastrand::Rng::with_seed(seed),
+            None => fastrand::Rng::new(),
+        };
+        let progress = if config.progress {
+            Some(Progress::new())
+        } else {
+            None
+        };
+        Ok(Filter {
+            reader,
+            config,
+            patterns,
+            prev_included_state: State::Init,
+            state: State::Init,
+            buf: Vec::with_capacity(config.buffersize_in_mb * 1024 * 1024),
+            stats: Stats::default(),
+            included_matched,
+            large_object: None,
+            dollar_quote: None,
+            split,
+            rng,
+            sink: io::sink(),
+            version_checked: config.min_version.is_none(),
+            column_edits_of_block: ColumnEdits::default(),
+            insert_prefix: vec![],
+            rows_in_insert: 0,
+            jsonl_table: String::new(),
+            jsonl_keys: vec![],
+            progress,
+            block_name: None,
+            row_fields: None,
+            transform: None,
+            truncated: false,
+            toc_section: None,
+            statement_excluded: false,
+            toc_owner: Owner::None,
+            line_number: 0,
+            lines_read: 0,
+        })
+    }
+
+    /// Reads the next line of the dump and writes it to `writer` if it is included, `false` at
+    /// the end of the dump.  An error says at which line of the dump it happened.
+    pub(crate) fn next_line<W: Write>(&mut self, writer: &mut W) -> Result<bool> {
+        self.line_number = self.lines_read + 1;
+        self.filter_line(writer).map_err(|e| {
+            if is_broken_pipe(&e) {
+                e
+            } else {
+                format!("line {}: {}", self.line_number, e).into()
+            }
+        })
+    }
+
+    fn filter_line<W: Write>(&mut self, writer: &mut W) -> Result<bool> {
+        let config = self.config;
+        self.buf.clear();
+
+        // the last line of the dump may lack its `\n`, all the checks on a line allow for that so
+        // a final `\.` still closes its copy block and a final data row is kept as is
+        let mut number_of_bytes_read = match config.stream_rows_over {
+            // a row is never buffered beyond the limit, the rest of it is streamed further down
+            Some(limit) if self.state.is_copy_block() => {
+                let limit = limit.max(MIN_STREAM_ROWS_OVER);
+                io::Read::take(&mut self.reader, limit as u64).read_until(b'\n', &mut self.buf)?
+            }
+            // reading one byte more than allowed tells a line that is too long
+            _ => match config.max_line_bytes {
+                Some(max) => {
+                    let read = io::Read::take(&mut self.reader, max as u64 + 1)
+                        .read_until(b'\n', &mut self.buf)?;
+                    if read > max {
+                        return Err(
+                            format!("a line is longer than the maximum of {} bytes", max).into(),
+                        );
+                    }
+                    read
+                }
+                None => self.reader.read_until(b'\n', &mut self.buf)?,
+            },
+        };
+        if number_of_bytes_read == 0 {
+            // the rows of a copy block without its `\.` still come out of their transform
+            if let Some(transform) = self.transform.take() {
+                let out = destination(&mut self.split, writer, &mut self.sink, self.state, true)?;
+                transform.finish(out)?;
+            }
+            return Ok(false);
+        }
+        self.lines_read += 1;
+        // the first line is told by its start like any other, without a byte order mark before it
+        if self.lines_read == 1 && self.buf.starts_with(BOM) {
+            self.buf.drain(..BOM.len());
+            if config.keep_bom && !config.to_jsonl {
+                writer.write_all(BOM)?;
+            }
+        }
+        // a column list wrapped over several lines is read up to the `;`, so the whole header is
+        // told apart from a statement
+        if self.state.is_between_statements()
+            && self.buf.starts_with(COPY_BLOCK_PREFIX)
+            && !is_end_of_statement(&self.buf)
+        {
+            let first_line = self.buf.len();
+            number_of_bytes_read +=
+                read_rest_of_statement(&mut self.reader, &mut self.buf, config.max_line_bytes)?;
+            self.lines_read += self.buf[first_line..]
+                .iter()
+                .filter(|&&b| b == b'\n')
+                .count() as u64;
+        }
+        let buf = &self.buf;
+        let is_partial = config.stream_rows_over.is_some()
+            && self.state.is_copy_block()
+            && !buf.ends_with(NEWLINE);
+        if let Some(progress) = &mut self.progress {
+            progress.add(number_of_bytes_read as u64);
+        }
+        let prev_state = self.state;
+        let mut state = prev_state.next_state(buf, config, &self.patterns)?;
+        let is_data = state.is_copy_block() && prev_state.is_copy_block();
+        if state == State::IncludedCopyBlock && !is_data {
+            let included_blocks = self.stats.copy_blocks.iter().filter(|b| b.included).count();
+            if config
+                .max_blocks
+                .is_some_and(|max| included_blocks as u64 >= max)
+            {
+                debug!(
+                    "copy block over the maximum of {:?} blocks",
+                    config.max_blocks
+                );
+                state = State::ExcludedCopyBlock;
+            }
+        }
+        if let (false, Some(min_version)) = (self.version_checked, &config.min_version) {
+            // the version is in the comments at the top, before the first statement
+            if let Some(version) = Version::dumped_from(buf) {
+                if version < *min_version {
+                    return Err(format!(
+                        "the dump is from PostgreSQL {}, below the minimum version {}",
+                        version, min_version
+                    )
+                    .into());
+                }
+                self.version_checked = true;
+            } else if !matches!(
+                state,
+                State::Comment | State::EmptyLine | State::ConsecutiveEmptyLine
+            ) {
+                return Err("the dump has no `-- Dumped from database version` comment".into());
+            }
+        }
+        let mut is_binary = false;
+        let mut rewritten_header = None;
+        if let State::Ddl | State::UnterminatedDdl | State::DollarQuoted = state {
+            // a function body is passed through as is, even its lines looking like comments
+            self.dollar_quote = dollar::dollar_quote_after(buf, self.dollar_quote.take());
+            state = match self.dollar_quote {
+                Some(_) => State::DollarQuoted,
+                None if state != State::DollarQuoted => state,
+                None if is_end_of_statement(buf) => State::Ddl,
+                None => State::UnterminatedDdl,
+            };
+        }
+        // the first line of a statement decides on all of its lines, a statement spanning several
+        // lines is dropped or kept whole
+        let excluded_statement = if prev_state.is_between_statements() && state.is_statement() {
+            self.statement_excluded = is_excluded_statement(buf, config);
+            self.statement_excluded
+        } else {
+            matches!(prev_state, State::UnterminatedDdl | State::DollarQuoted)
+                && self.statement_excluded
+        };
+        if let State::LargeObjectCreate | State::LargeObject | State::LargeObjectWrite = state {
+            let function = large_object_function(buf);
+            if config.strict_large_objects && !LARGE_OBJECT_FUNCTIONS.contains(&&*function) {
+                return Err(format!("unknown large object function {}", function).into());
+            }
+            *self
+                .stats
+                .large_object_functions
+                .entry(function)
+                .or_default() += 1;
+            // lowrite and lo_close work on the large object opened last
+            if state == State::LargeObjectCreate || buf.starts_with(LO_OPEN) {
+                self.large_object = large_object_oid(buf);
+            }
+            if self
+                .large_object
+                .is_some_and(|oid| config.excluded_large_objects.contains(&oid))
+            {
+                state = State::ExcludedLargeObject;
+            }
+        }
+        if is_data {
+            if let Some(copy_block) = self.stats.copy_blocks.last_mut() {
+                copy_block.rows += 1;
+                copy_block.bytes += buf.len() as u64;
+                if state == State::IncludedCopyBlock {
+                    if config.validate_rows && !is_partial {
+                        let fields = *self.row_fields.get_or_insert_with(|| {
+                            trim_newline(buf).split(|&b| b == b'\t').count()
+                        });
+                        columns::validate_row(buf, fields, config.null_string.as_bytes()).map_err(
+                            |e| {
+                                format!(
+                                    "invalid row in the copy block of {}: {}",
+                                    copy_block.name, e
+                                )
+                            },
+                        )?;
+                    }
+                    let kept = self.column_edits_of_block.keeps(buf);
+                    let below_max_rows = match config.max_rows {
+                        Some(max) => copy_block.included_rows < max,
+                        None => true,
+                    };
+                    let sampled = match config.sample {
+                        Some(fraction) => self.rng.f64() < fraction,
+                        None => true,
+                    };
+                    if kept && below_max_rows && sampled && !self.truncated {
+                        copy_block.included_rows += 1;
+                        copy_block.included_bytes += buf.len() as u64;
+                    } else {
+                        state = State::ExcludedRow;
+                    }
+                }
+            }
+        } else if state.is_copy_block() {
+            if let Some(header) = CopyHeader::parse(buf) {
+                is_binary = header.is_binary();
+                let name = copy_block_name(&header).into_owned();
+                for (pattern, matched) in self
+                    .patterns
+                    .included
+                    .iter()
+                    .zip(self.included_matched.iter_mut())
+                {
+                    *matched = *matched || pattern.matches(&name);
+                }
+                if is_binary && config.to_inserts && state == State::IncludedCopyBlock {
+                    return Err(format!(
+                        "can not turn the binary copy block of {} into inserts",
+                        name
+                    )
+                    .into());
+                }
+                if is_binary && config.to_jsonl && state == State::IncludedCopyBlock {
+                    return Err(format!(
+                        "can not turn the binary copy block of {} into JSON",
+                        name
+                    )
+                    .into());
+                }
+                // the rows of a binary copy block are copied whole, none of them are left out
+                let leaves_out_rows = config.max_rows.is_some() || config.sample.is_some();
+                if is_binary && leaves_out_rows && state == State::IncludedCopyBlock {
+                    return Err(format!(
+                        "can not keep only some rows of the binary copy block of {}",
+                        name
+                    )
+                    .into());
+                }
+                let transform = self
+                    .patterns
+                    .transforms
+                    .iter()
+                    .find(|(table, _)| table.matches(&name));
+                if let (Some((_, command)), State::IncludedCopyBlock) = (transform, state) {
+                    if is_binary {
+                        return Err(
+                            format!("can not transform the binary copy block of {}", name).into(),
+                        );
+                    }
+                    self.transform = Some(Transform::spawn(command)?);
+                }
+                let column_list: Vec<String> = header
+                    .columns
+                    .map(columns::column_list)
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|column| String::from_utf8_lossy(column).into_owned())
+                    .collect();
+                self.row_fields = header.columns.map(|_| column_list.len());
+                self.truncated = self.patterns.truncated.iter().any(|p| p.matches(&name));
+                let (edits, header) = column_edits(buf, &header, &name, &self.patterns, config)?;
+                self.column_edits_of_block = edits;
+                rewritten_header = header;
+                if let (Some(split), State::IncludedCopyBlock) = (&mut self.split, state) {
+                    split.start_copy_block(&name)?;
+                }
+                let reason = match copy_block_reason(&name, config, &self.patterns) {
+                    Reason::Included if state != State::IncludedCopyBlock => Reason::MaxBlocks,
+                    reason => reason,
+                };
+                self.stats.copy_blocks.push(CopyBlockStats {
+                    name,
+                    columns: column_list,
+                    included: state == State::IncludedCopyBlock,
+                    rows: 0,
+                    included_rows: 0,
+                    bytes: 0,
+                    included_bytes: 0,
+                    reason,
+                });
+            }
+        }
+        self.state = state;
+        if excluded_statement {
+            state = State::ExcludedStatement;
+        }
+        if config.tables_only_matching_data && !is_data {
+            match state {
+                State::Comment => {
+                    self.toc_owner = Owner::of_entry(buf).unwrap_or(self.toc_owner.clone())
+                }
+                _ if self.toc_owner == Owner::Index && state.is_statement() => {
+                    self.toc_owner = toc::indexed_table(buf).map_or(Owner::None, Owner::Table)
+                }
+                _ if self.toc_owner == Owner::SequenceOwnedBy && state.is_statement() => {
+                    self.toc_owner = toc::sequence_owner(buf).map_or(Owner::None, Owner::Table)
+                }
+                _ => {}
+            }
+            // the settings before a statement carry over to the statements after it
+            let is_setting = matches!(state, State::Set | State::SearchPath);
+            if let (Owner::Table(table), false) = (&self.toc_owner, is_setting) {
+                if state.is_statement() && !is_table_kept(table, &self.patterns) {
+                    state = State::ExcludedStatement;
+                }
+            }
+        }
+        if config.use_toc && !is_data {
+            // a copy block is data, wherever the table of contents entry before it is
+            match state {
+                State::Comment => self.toc_section = Section::of_entry(buf).or(self.toc_section),
+                State::IncludedCopyBlock | State::ExcludedCopyBlock => {
+                    self.toc_section = Some(Section::Data)
+                }
+                _ => {}
+            }
+        }
+        let included = state.must_include(config, &self.prev_included_state)
+            && match self.toc_section {
+                _ if state == State::CompletionMarker => true,
+                // the setval options decide over the section of the sequence set entry
+                _ if state == State::SetVal && (config.keep_setval || config.drop_setval) => true,
+                Some(Section::Schema) if config.use_toc => !config.data_only,
+                Some(Section::Data) if config.use_toc => !config.schema_only,
+                _ => true,
+            };
+        if included {
+            // copy data is always written verbatim, only statements and headers are rewritten
+            let mut line = match rewritten_header {
+                Some(header) => Cow::Owned(header),
+                None => Cow::Borrowed(&buf[..]),
+            };
+            if is_data && !self.column_edits_of_block.is_empty() {
+                line = Cow::Owned(self.column_edits_of_block.edit_row(&line));
+            }
+            // a renamed table is matched by its old schema, before that is renamed
+            if !is_data && !config.rename_tables.is_empty() {
+                line = Cow::Owned(rename::rename_tables(&line, &config.rename_tables));
+            }
+            if !is_data && !config.rename_schemas.is_empty() {
+                line = Cow::Owned(rename::rename_schemas(&line, &config.rename_schemas));
+            }
+            if state == State::SearchPath && !config.rename_schemas.is_empty() {
+                line = Cow::Owned(rename::rename_search_path(&line, &config.rename_schemas));
+            }
+            let is_statement_or_header = state.is_statement() || state.is_copy_block();
+            if !is_data && is_statement_or_header && config.lowercase_identifiers {
+                line = Cow::Owned(rename::lowercase_identifiers(&line));
+                if state == State::SearchPath {
+                    line = Cow::Owned(rename::lowercase_search_path(&line));
+                }
+            }
+            if state == State::LargeObjectWrite && config.truncate_large_objects {
+                line = Cow::Owned(truncate_lowrite(&line));
+            }
+            // the hooks go around the copy block, whatever it was turned into
+            let mut before = None;
+            let mut after = None;
+            if state == State::IncludedCopyBlock && !is_data {
+                self.block_name = CopyHeader::parse(&line).map(|header| header.name.to_vec());
+                before = config
+                    .before_block
+                    .as_deref()
+                    .zip(self.block_name.as_deref());
+            } else if state == State::EndOfIncludedCopyBlock {
+                after = config.after_block.as_deref().zip(self.block_name.take());
+            }
+            if config.to_inserts {
+                // the header and terminator give way to an insert per row
+                match state {
+                    State::IncludedCopyBlock if is_data => {
+                        // the rows of an insert are ended by a `,` as long as more may follow
+                        let mut insert = if self.rows_in_insert == 0 {
+                            self.insert_prefix.clone()
+                        } else {
+                            b",\n".to_vec()
+                        };
+                        insert.extend_from_slice(&inserts::values(
+                            &line,
+                            config.null_string.as_bytes(),
+                        ));
+                        self.rows_in_insert += 1;
+                        if self.rows_in_insert >= config.insert_batch {
+                            insert.extend_from_slice(b";\n");
+                            self.rows_in_insert = 0;
+                        }
+                        line = Cow::Owned(insert);
+                    }
+                    State::IncludedCopyBlock => {
+                        self.insert_prefix = CopyHeader::parse(&line)
+                            .map(|header| inserts::insert_prefix(&header))
+                            .unwrap_or_default();
+                        line = Cow::Borrowed(&[]);
+                    }
+                    State::EndOfIncludedCopyBlock if self.rows_in_insert > 0 => {
+                        line = Cow::Borrowed(b";\n");
+                        self.rows_in_insert = 0;
+                    }
+                    State::EndOfIncludedCopyBlock => line = Cow::Borrowed(&[]),
+                    _ => {}
+                }
+            }
+            if config.to_jsonl {
+                // only the rows are left, as an object each
+                match state {
+                    State::IncludedCopyBlock if is_data => {
+                        line = Cow::Owned(jsonl::object(
+                            &self.jsonl_table,
+                            &self.jsonl_keys,
+                            &line,
+                            config.null_string.as_bytes(),
+                        ));
+                    }
+                    State::IncludedCopyBlock => {
+                        if let Some(header) = CopyHeader::parse(&line) {
+                            self.jsonl_table = copy_block_name(&header).into_owned();
+                            self.jsonl_keys = jsonl::keys(&header);
+                        }
+                        line = Cow::Borrowed(&[]);
+                    }
+                    _ => line = Cow::Borrowed(&[]),
+                }
+            }
+            debug_assert!(
+                config.rewrites_lines() || *line == buf[..],
+                "an included line is only changed by an option rewriting lines"
+            );
+            // a line rewritten to nothing, like the header with `to_inserts`, does not separate
+            // the empty lines around it
+            if !line.is_empty() {
+                self.prev_included_state = state;
+            }
+            let out = destination(&mut self.split, writer, &mut self.sink, state, true)?;
+            if let Some((template, name)) = before {
+                out.write_all(&hook(template, name))?;
+            }
+            match &mut self.transform {
+                Some(transform) if is_data => {
+                    transform.write_all(&line)?;
+                    transform.write_output(out)?;
+                }
+                _ => {
+                    if state == State::EndOfIncludedCopyBlock {
+                        if let Some(transform) = self.transform.take() {
+                            transform.finish(out)?;
+                        }
+                    }
+                    out.write_all(&line)?;
+                }
+            }
+            if let Some((template, name)) = after {
+                out.write_all(&hook(template, &name))?;
+            }
+        }
+        let mut line_len = buf.len() as u64;
+        if is_partial {
+            let out = match &mut self.transform {
+                Some(transform) if included => transform,
+                _ => destination(&mut self.split, writer, &mut self.sink, state, included)?,
+            };
+            let rest = copy_rest_of_line(&mut self.reader, out)?;
+            line_len += rest;
+            if let Some(progress) = &mut self.progress {
+                progress.add(rest);
+            }
+            if let Some(copy_block) = self.stats.copy_blocks.last_mut() {
+                copy_block.bytes += rest;
+                if state == State::IncludedCopyBlock {
+                    copy_block.included_bytes += rest;
+                }
+            }
+        }
+        self.stats.count(state, line_len, included);
+        if is_binary {
+            let out = destination(&mut self.split, writer, &mut self.sink, state, included)?;
+            let data = binary::copy_binary_data(&mut self.reader, out, !self.truncated)?;
+            self.stats
+                .count(state, data.bytes - data.dropped_bytes, included);
+            if data.dropped_bytes > 0 {
+                self.stats
+                    .count(State::ExcludedRow, data.dropped_bytes, false);
+            }
+            if let Some(progress) = &mut self.progress {
+                progress.add(data.bytes);
+            }
+            if let Some(copy_block) = self.stats.copy_blocks.last_mut() {
+                copy_block.rows = data.tuples;
+                copy_block.included_rows = if included && !self.truncated {
+                    data.tuples
+                } else {
+                    0
+                };
+                copy_block.bytes = data.bytes;
+                copy_block.included_bytes = if included {
+                    data.bytes - data.dropped_bytes
+                } else {
+                    0
+                };
+            }
+            // the trailer ends the block, a `\.` line may follow and goes with it
+            self.state = match state {
+                State::IncludedCopyBlock => State::EndOfIncludedCopyBlock,
+                _ => State::EndOfExcludedCopyBlock,
+            };
+        }
+        Ok(true)
+    }
+
+    /// Ends the filtering once the whole dump is read, returning the statistics.
+    pub(crate) fn finish(mut self) -> Result<Stats> {
+        if let Some(split) = &mut self.split {
+            split.finish()?;
+        }
+        if let Some(progress) = &self.progress {
+            progress.finish();
+        }
+        if self.config.strict && self.state.is_copy_block() {
+            let name = self
+                .stats
+                .copy_blocks
+                .last()
+                .map_or("", |copy_block| &copy_block.name);
+            return Err(format!(
+                "the dump ends inside the copy block of {}, its \\. terminator is missing",
+                name
+            )
+            .into());
+        }
+        self.stats.unmatched_included_copy_blocks = self
+            .patterns
+            .included
+            .iter()
+            .zip(self.included_matched)
+            .filter(|(_, matched)| !matched)
+            .map(|(pattern, _)| pattern.block.clone())
+            .collect();
+        // the schemas only matter to the included and excluded copy blocks
+        if !self.patterns.included.is_empty() || !self.patterns.excluded.is_empty() {
+            let (config, copy_blocks) = (self.config, &self.stats.copy_blocks);
+            self.stats.unmatched_schemas = config
+                .schemas
+                .iter()
+                .filter(|schema| {
+                    !copy_blocks
+                        .iter()
+                        .any(|copy_block| is_in_schema(&copy_block.name, schema, config))
+                })
+                .cloned()
+                .collect();
+        }
+        Ok(self.stats)
+    }
+}
@@ -0,0 +1,64 @@
+use std::io::BufRead;
+
+use crate::{Filter, FilterConfig, Result, Stats};
+
+/// The lines of the filtered dump one at a time, as [`filter`](crate::filter) would write them.
+///
+/// Lines that go to the files of `split_dir` are not yielded.  The rows of a binary copy block,
+/// and with `stream_rows_over` a row over the limit, come in pieces rather than as lines.
+pub struct FilteredLines<'c, R> {
+    filter: Option<Filter<'c, R>>,
+    error: Option<crate::Error>,
+    stats: Option<Stats>,
+}
+
+impl<'c, R: BufRead> FilteredLines<'c, R> {
+    /// Filters the dump read from `reader` according to `config`, an invalid config is the first
+    /// error yielded.
+    pub fn new(reader: R, config: &'c FilterConfig) -> FilteredLines<'c, R> {
+        let (filter, error) = match Filter::new(reader, config) {
+            Ok(filter) => (Some(filter), None),
+            Err(error) => (None, Some(error)),
+        };
+        FilteredLines {
+            filter,
+            error,
+            stats: None,
+        }
+    }
+
+    /// How many lines and bytes were included or dropped, once all lines were yielded.
+    pub fn stats(&self) -> Option<&Stats> {
+        self.stats.as_ref()
+    }
+}
+
+impl<R: BufRead> Iterator for FilteredLines<'_, R> {
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Result<Vec<u8>>> {
+        if let Some(error) = self.error.take() {
+            return Some(Err(error));
+        }
+        let filter = self.filter.as_mut()?;
+        let mut line = Vec::new();
+        loop {
+            match filter.next_line(&mut line) {
+                Ok(true) if line.is_empty() => continue,
+                Ok(true) => return Some(Ok(line)),
+                Ok(false) => match self.filter.take()?.finish() {
+                    Ok(stats) => {
+                        self.stats = Some(stats);
+                        return None;
+                    }
+                    Err(error) => return Some(Err(error)),
+                },
+                // like `filter`, the first error ends the filtering
+                Err(error) => {
+                    self.filter = None;
+                    return Some(Err(error));
+                }
+            }
+        }
+    }
+}
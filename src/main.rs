@@ -1,154 +1,1182 @@
-use std::io::{BufReader, BufWriter, Write};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, IsTerminal, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Instant;
 use std::{io, io::prelude::*};
+use structopt::clap::ArgMatches;
 use structopt::StructOpt;
 
-#[macro_use]
-extern crate lazy_static;
+use flate2::bufread::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use pgdump_filter::{
+    copy_block_boundary, fill_buf, filter, filter_parts, is_broken_pipe, FilterConfig, Result,
+    Stats, Version,
+};
 
-pub type Error = Box<dyn std::error::Error + Send + Sync>;
-pub type Result<T> = std::result::Result<T, Error>;
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
 
 #[derive(StructOpt, Debug)]
 #[structopt(name = "pgdump_filter")]
 struct Options {
-    /// Exclude the listed copy block(s)
+    /// Exclude the listed copy block(s), `*` and `?` act as wildcards unless --regex is given
     #[structopt(
         short = "e",
         long = "excluded_copy_blocks",
-        conflicts_with = "included_copy_blocks"
+        conflicts_with = "included-copy-blocks"
     )]
     excluded_copy_blocks: Vec<String>,
-    /// Include the listed copy block(s)
+    /// Include the listed copy block(s), `*` and `?` act as wildcards unless --regex is given
     #[structopt(short = "i", long = "included_copy_blocks")]
     included_copy_blocks: Vec<String>,
-    /// Flag to exclude large object operations (lo_read, lowrite, lo_open, ...)
-    #[structopt(short = "l", long = "exclude_large_objects")]
+    /// Exclude the copy block(s) listed in this file, one per line
+    #[structopt(long = "excluded_copy_blocks_file", parse(from_os_str))]
+    excluded_copy_blocks_file: Option<PathBuf>,
+    /// Include the copy block(s) listed in this file, one per line
+    #[structopt(long = "included_copy_blocks_file", parse(from_os_str))]
+    included_copy_blocks_file: Option<PathBuf>,
+    /// Include the copy block(s) in the first column of the output of a psql query saved in this
+    /// file, without its header, ruler and "(42 rows)" footer
+    #[structopt(long = "include-from-query-output", parse(from_os_str))]
+    include_from_query_output: Option<PathBuf>,
+    /// Flag to exclude large object operations (lo_read, lowrite, lo_open, ...) and the copy blocks
+    /// of pg_largeobject and pg_largeobject_metadata
+    #[structopt(
+        short = "l",
+        long = "exclude_large_objects",
+        conflicts_with = "truncate-large-objects"
+    )]
     exclude_large_objects: bool,
-    /// Schema of the objects
+    /// Keep the large objects but make them empty by dropping the data of the lowrite calls
+    #[structopt(long = "truncate-large-objects")]
+    truncate_large_objects: bool,
+    /// Exclude the large object(s) with these oids, with their owner, comment and privileges
+    #[structopt(long = "exclude-lo-oid")]
+    exclude_lo_oid: Vec<u32>,
+    /// Fail on a large object function pg_dump does not call, and list the ones called
+    #[structopt(long = "strict-lo")]
+    strict_lo: bool,
+    /// Treat the included/excluded copy blocks as regular expressions matched against the table name
+    #[structopt(long = "regex")]
+    regex: bool,
+    /// Swap the included and excluded copy blocks
+    #[structopt(long = "invert")]
+    invert: bool,
+    /// Match the copy blocks and schemas case sensitively, e.g. for quoted `"MixedCase"` tables
+    #[structopt(long = "case-sensitive")]
+    case_sensitive: bool,
+    /// Match the copy blocks that are plain names on the start of the table name, e.g. `events`
+    /// matches the partitions `events_2023` and `events_2024`, and `events_archive` too
+    #[structopt(long = "prefix-match")]
+    prefix_match: bool,
+    /// Match the copy blocks of tables without a schema in the dump on their table name alone
+    #[structopt(long = "allow-unqualified")]
+    allow_unqualified: bool,
+    /// Schema(s) of the objects
     #[structopt(default_value = "public", short = "s", long = "schema")]
-    schema: String,
+    schema: Vec<String>,
     #[structopt(
         default_value = "32",
         short = "b",
         long = "buffersize",
-        help = "size of buffer in MB.  make sure this is enough to hold the longest line in the file."
+        help = "initial size of the line buffer in MB, it grows to fit longer lines."
     )]
     buffersize_in_mb: usize,
+    /// Size of the buffer the input is read through in KB, apart from the line buffer
+    #[structopt(
+        default_value = "64",
+        long = "read-buffer-kb",
+        parse(try_from_str = parse_positive)
+    )]
+    read_buffer_kb: usize,
+    /// Size of the buffer the output is written through in KB
+    #[structopt(
+        default_value = "64",
+        long = "write-buffer-kb",
+        parse(try_from_str = parse_positive)
+    )]
+    write_buffer_kb: usize,
+    /// Read the dump from these files, one after the other, instead of stdin
+    #[structopt(short = "f", long = "input", parse(from_os_str))]
+    input: Vec<PathBuf>,
+    /// Start at the first copy block from this byte of the --input on, instead of at the start.
+    /// The copy blocks are told by the "-- Data for Name:" comment pg_dump writes before them
+    #[structopt(long = "start-byte")]
+    start_byte: Option<u64>,
+    /// End before the first copy block from this byte of the --input on, instead of at the end.
+    /// Filtering the parts between a few offsets apart gives the output of the whole dump in parts
+    #[structopt(long = "end-byte")]
+    end_byte: Option<u64>,
+    /// Filter the --input in this many parts between copy blocks at once, on a thread each
+    #[structopt(long = "jobs", parse(try_from_str = parse_positive))]
+    jobs: Option<usize>,
+    /// Write the filtered dump to this file instead of stdout
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    output: Option<PathBuf>,
+    /// Compress the filtered dump with gzip or zstd, implied when --output ends in .gz or .zst.
+    /// Without a value the suffix of --output decides, gzip if it has neither
+    #[structopt(short = "z", long = "compress", require_equals = true)]
+    compress: Option<Option<Codec>>,
+    /// Print statistics about the included and dropped lines to stderr
+    #[structopt(long = "stats")]
+    stats: bool,
+    /// Write every table of the dump as JSON to this file, with whether and why it was included or
+    /// excluded and its number of rows, `-` for stderr
+    #[structopt(long = "manifest", parse(from_os_str))]
+    manifest: Option<PathBuf>,
+    /// Write the statistics as JSON to this file, `-` for stderr
+    #[structopt(long = "stats-json", parse(from_os_str))]
+    stats_json: Option<PathBuf>,
+    /// Write nothing and run no --transform, only report on stderr which copy blocks would be
+    /// included and excluded
+    #[structopt(long = "dry-run")]
+    dry_run: bool,
+    /// Write nothing to stderr except the error a run fails with, overrides --stats, --progress
+    /// and --timing
+    #[structopt(long = "quiet")]
+    quiet: bool,
+    /// Print the number of rows in the output of every included copy block to stderr
+    #[structopt(long = "count-rows")]
+    count_rows: bool,
+    /// Print the number of bytes processed and the throughput to stderr while filtering
+    #[structopt(long = "progress")]
+    progress: bool,
+    /// Print how long the filtering took, the number of bytes read and the throughput to stderr
+    #[structopt(long = "timing")]
+    timing: bool,
+    /// Only list the tables of the copy blocks in the dump, one per line
+    #[structopt(long = "list")]
+    list: bool,
+    /// Only report the tables of the copy blocks in the dump with their rows and columns
+    #[structopt(long = "summary-only")]
+    summary_only: bool,
+    /// Rename a schema in the statements and copy block headers, as old=new
+    #[structopt(long = "rename-schema", parse(try_from_str = parse_rename))]
+    rename_schema: Vec<(String, String)>,
+    /// Rename a table in the statements and copy block headers, as schema.table=new, the table
+    /// stays in its schema
+    #[structopt(long = "rename-table", parse(try_from_str = parse_rename))]
+    rename_table: Vec<(String, String)>,
+    /// Fold the schema qualified and the quoted names in the statements and copy block headers
+    /// to lowercase, e.g. "Sales"."Order" to "sales"."order"
+    #[structopt(long = "lowercase-identifiers")]
+    lowercase_identifiers: bool,
+    /// Keep at most this many rows per copy block
+    #[structopt(long = "max-rows")]
+    max_rows: Option<u64>,
+    /// Keep the data of the first this many included copy blocks only, e.g. for a smoke test
+    #[structopt(long = "max-blocks")]
+    max_blocks: Option<u64>,
+    /// Keep each row of a copy block with this probability (between 0 and 1)
+    #[structopt(long = "sample", parse(try_from_str = parse_fraction))]
+    sample: Option<f64>,
+    /// Seed for --sample, to get the same rows on every run
+    #[structopt(long = "seed")]
+    seed: Option<u64>,
+    /// Fail if any of the included copy blocks is not in the dump
+    #[structopt(long = "require-all-included")]
+    require_all_included: bool,
+    /// Keep the comments, e.g. the `-- Data for Name: ...` section markers
+    #[structopt(long = "keep-comments")]
+    keep_comments: bool,
+    /// Keep the "-- PostgreSQL database dump complete" comment at the end of the dump, even when
+    /// the other comments are dropped or with --data-only
+    #[structopt(long = "keep-completion-marker")]
+    keep_completion_marker: bool,
+    /// Start the output with the UTF-8 byte order mark the dump starts with, it is dropped
+    /// otherwise
+    #[structopt(long = "keep-bom")]
+    keep_bom: bool,
+    /// Treat the lines starting with this as comments, e.g. `#`.  Replaces the default, give
+    /// `--comment-prefix=--` as well to keep the comments of pg_dump
+    #[structopt(default_value = "--", long = "comment-prefix")]
+    comment_prefix: Vec<String>,
+    /// Keep runs of empty lines instead of collapsing them into one
+    #[structopt(long = "keep-blank-lines")]
+    keep_blank_lines: bool,
+    /// Drop the `SET ...` statements, except `SET search_path`
+    #[structopt(long = "strip-set")]
+    strip_set: bool,
+    /// Drop the statements setting the search path (`SET search_path` and `set_config`)
+    #[structopt(long = "strip-search-path")]
+    strip_search_path: bool,
+    /// Drop the `GRANT ...` and `REVOKE ...` statements
+    #[structopt(long = "strip-grants")]
+    strip_grants: bool,
+    /// Drop the `ALTER TABLE/SEQUENCE ... OWNER TO ...` statements
+    #[structopt(long = "strip-owner")]
+    strip_owner: bool,
+    /// Drop the statements starting with this, e.g. "CREATE INDEX", ignoring case
+    #[structopt(long = "exclude-statements")]
+    exclude_statements: Vec<String>,
+    /// Drop the statements starting with none of these, e.g. "CREATE TABLE,ALTER TABLE",
+    /// ignoring case.  The copy blocks are kept or dropped by -i and -e
+    #[structopt(long = "only-statements", use_delimiter = true)]
+    only_statements: Vec<String>,
+    /// Keep the `SELECT pg_catalog.setval(...)` calls, even with --schema-only
+    #[structopt(long = "keep-setval", conflicts_with = "drop-setval")]
+    keep_setval: bool,
+    /// Drop the `SELECT pg_catalog.setval(...)` calls, even with --data-only
+    #[structopt(long = "drop-setval")]
+    drop_setval: bool,
+    /// Drop all copy blocks, keeping only the schema
+    #[structopt(long = "schema-only", conflicts_with = "data-only")]
+    schema_only: bool,
+    /// Drop the ddl, comments and empty lines, keeping only the data
+    #[structopt(long = "data-only")]
+    data_only: bool,
+    /// Tell the schema from the data for --schema-only and --data-only by the "-- Name: ...;
+    /// Type: ...;" comments pg_dump writes before every object, instead of by the statements
+    #[structopt(long = "use-toc")]
+    use_toc: bool,
+    /// Drop the CREATE TABLE statements, indexes and constraints of the tables whose copy blocks
+    /// are excluded or not included, told by the "-- Name: ...; Type: ...;" comments of pg_dump
+    #[structopt(long = "tables-only-matching-data")]
+    tables_only_matching_data: bool,
+    /// Fail when the dump ends inside a copy block, e.g. because it was truncated
+    #[structopt(long = "strict")]
+    strict: bool,
+    /// Fail on a row of an included copy block with another number of fields than its header has
+    /// columns, or with an escape pg_dump does not write, e.g. in a corrupt dump
+    #[structopt(long = "validate-rows")]
+    validate_rows: bool,
+    /// Write the included copy blocks to a `schema.table.sql` file each in this directory and
+    /// the other statements to `schema.sql` in it, instead of to the output
+    #[structopt(long = "split-dir", parse(from_os_str))]
+    split_dir: Option<PathBuf>,
+    /// Stream the data rows longer than this many bytes to the output in chunks, instead of
+    /// reading them into memory whole.  Not with --drop-column, --mask, --where-equals,
+    /// --where-in or --to-inserts, which need the whole row
+    #[structopt(long = "stream-rows-over")]
+    stream_rows_over: Option<usize>,
+    /// Fail unless the dump is from at least this PostgreSQL version, e.g. 14 or 9.6
+    #[structopt(long = "min-version")]
+    min_version: Option<Version>,
+    /// Leave this column out of its copy block, as schema.table.column
+    #[structopt(long = "drop-column")]
+    drop_column: Vec<String>,
+    /// Replace the values of this column with `***`, as schema.table.column
+    #[structopt(long = "mask")]
+    mask: Vec<String>,
+    /// Keep only the rows of a table with this value in a column, as schema.table.column=value,
+    /// the --null-string for null.  Several on the same table must all hold
+    #[structopt(long = "where")]
+    where_equals: Vec<String>,
+    /// Keep only the rows of a table with one of these values in a column, as
+    /// schema.table.column=value,value,...  The values can not contain a comma
+    #[structopt(long = "where-in")]
+    where_in: Vec<String>,
+    /// How a null is written in the copy blocks, for a dump made with another NULL option.
+    /// Masked nulls stay null, --to-inserts writes NULL for it and --where takes it for null
+    #[structopt(long = "null-string", default_value = "\\N")]
+    null_string: String,
+    /// Mask the values with a hash instead of `***`, so equal values stay equal.  The hash is
+    /// keyed with --mask-key, or a random key that differs on every run
+    #[structopt(long = "mask-hash")]
+    mask_hash: bool,
+    /// Key of the --mask-hash hash, the same key gives the same hashes on every run.  The values
+    /// of a column with few values can be guessed by anyone who has the key
+    #[structopt(long = "mask-key")]
+    mask_key: Option<String>,
+    /// Write an `INSERT INTO ... VALUES (...);` statement per row instead of the copy blocks
+    #[structopt(long = "to-inserts")]
+    to_inserts: bool,
+    /// Write the rows of the included copy blocks as JSON objects, one per line, instead of the
+    /// dump, like {"table":"public.foo","row":{"id":"1"}}
+    #[structopt(long = "to-jsonl", conflicts_with = "to-inserts")]
+    to_jsonl: bool,
+    /// Put up to this many rows in each `INSERT` statement of --to-inserts
+    #[structopt(long = "insert-batch", requires = "to-inserts", parse(try_from_str = parse_positive))]
+    insert_batch: Option<usize>,
+    /// Fail on a line longer than this many bytes, e.g. in a corrupt dump, instead of reading it
+    /// into memory whole
+    #[structopt(long = "max-line-bytes")]
+    max_line_bytes: Option<usize>,
+    /// Write this statement before every included copy block, `%s` standing for its table name,
+    /// e.g. "TRUNCATE %s;"
+    #[structopt(long = "before-block")]
+    before_block: Option<String>,
+    /// Write this statement after every included copy block, `%s` standing for its table name,
+    /// e.g. "ANALYZE %s;"
+    #[structopt(long = "after-block")]
+    after_block: Option<String>,
+    /// Pipe the rows of a table through a shell command, as schema.table=command, e.g.
+    /// "public.users=tr a-z A-Z", run by sh or by cmd on Windows.  Its output takes the place of
+    /// the rows
+    #[structopt(long = "transform", parse(try_from_str = parse_transform))]
+    transform: Vec<(String, String)>,
+    /// Keep the copy block of this table, as schema.table or a table in the --schema(s), without
+    /// any rows: its header and "\." stay, unlike with --exclude
+    #[structopt(long = "truncate-table")]
+    truncate_table: Vec<String>,
+    /// Read the options from this TOML file, options on the command line take precedence
+    #[structopt(long = "config", parse(from_os_str))]
+    config: Option<PathBuf>,
 }
 
-#[derive(Debug, Clone, PartialEq, Copy)]
-pub enum State {
-    Init,
-    Comment,
-    EmptyLine,
-    ConsecutiveEmptyLine,
-    ExcludedCopyBlock,
-    EndOfExcludedCopyBlock,
-    LargeObject,
-    Statement,
-}
-
-lazy_static! {
-    static ref COMMENT: &'static [u8] = b"--";
-    static ref END_OF_COPY_BLOCK: &'static [u8] = b"\\.";
-    static ref NEWLINE: &'static [u8] = b"\n";
-    static ref COPY_BLOCK_PREFIX: &'static [u8] = b"COPY ";
-    static ref COPY_BLOCK_SUFFIX: &'static [u8] = b"FROM stdin;\n";
-    static ref LO_CREATE: &'static [u8] = b"SELECT pg_catalog.lo_create";
-    static ref LO_FN: &'static [u8] = b"SELECT pg_catalog.lo_";
-    static ref LO_WRITE: &'static [u8] = b"SELECT pg_catalog.lowrite";
-}
-impl State {
-    fn next_state(&self, buf: &[u8], opts: &Options) -> Result<State> {
-        match buf {
-            // keep the lo_create calls (oid colums in tables must work)
-            buf if buf.starts_with(*LO_CREATE) => Ok(State::Statement),
-            buf if buf.starts_with(*LO_FN) || buf.starts_with(*LO_WRITE) => Ok(State::LargeObject),
-            buf if buf.starts_with(*NEWLINE) => match self {
-                State::EmptyLine => Ok(State::ConsecutiveEmptyLine),
-                State::ConsecutiveEmptyLine => Ok(State::ConsecutiveEmptyLine),
-                _ => Ok(State::EmptyLine),
-            },
-            buf if buf.starts_with(*COMMENT) => Ok(State::Comment),
-            buf if buf.starts_with(*END_OF_COPY_BLOCK) => match self {
-                State::ExcludedCopyBlock => Ok(State::EndOfExcludedCopyBlock),
-                state => Ok(*state),
-            },
-            buf if buf.starts_with(*COPY_BLOCK_PREFIX) && buf.ends_with(*COPY_BLOCK_SUFFIX) => {
-                let l = String::from_utf8(buf.to_vec())?.to_lowercase();
-                match l {
-                    l if opts.excluded_copy_blocks.iter().any(|excluded_block| {
-                        l.contains(
-                            &format!("COPY {}.{} ", opts.schema, excluded_block).to_lowercase(),
-                        )
-                    }) =>
-                    {
-                        Ok(State::ExcludedCopyBlock)
-                    }
-                    l if !opts.included_copy_blocks.is_empty()
-                        && opts
-                            .included_copy_blocks
-                            .iter()
-                            .find(|&included_block| {
-                                l.to_ascii_lowercase().contains(
-                                    &format!("COPY {}.{} ", opts.schema, included_block)
-                                        .to_lowercase(),
-                                )
-                            })
-                            .is_none() =>
-                    {
-                        Ok(State::ExcludedCopyBlock)
+/// Options read from a `--config` file, with the same names as the fields of [`Options`], e.g.
+///
+/// ```toml
+/// schema = ["public", "audit"]
+/// excluded_copy_blocks = ["*_log"]
+/// strip_owner = true
+/// ```
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    excluded_copy_blocks: Option<Vec<String>>,
+    included_copy_blocks: Option<Vec<String>>,
+    excluded_copy_blocks_file: Option<PathBuf>,
+    included_copy_blocks_file: Option<PathBuf>,
+    include_from_query_output: Option<PathBuf>,
+    exclude_large_objects: Option<bool>,
+    truncate_large_objects: Option<bool>,
+    exclude_lo_oid: Option<Vec<u32>>,
+    strict_lo: Option<bool>,
+    regex: Option<bool>,
+    case_sensitive: Option<bool>,
+    prefix_match: Option<bool>,
+    allow_unqualified: Option<bool>,
+    invert: Option<bool>,
+    schema: Option<Vec<String>>,
+    buffersize_in_mb: Option<usize>,
+    read_buffer_kb: Option<usize>,
+    write_buffer_kb: Option<usize>,
+    input: Option<Vec<PathBuf>>,
+    output: Option<PathBuf>,
+    compress: Option<CompressSetting>,
+    stats: Option<bool>,
+    stats_json: Option<PathBuf>,
+    manifest: Option<PathBuf>,
+    progress: Option<bool>,
+    timing: Option<bool>,
+    quiet: Option<bool>,
+    count_rows: Option<bool>,
+    dry_run: Option<bool>,
+    list: Option<bool>,
+    summary_only: Option<bool>,
+    rename_schema: Option<Vec<String>>,
+    rename_table: Option<Vec<String>>,
+    lowercase_identifiers: Option<bool>,
+    max_rows: Option<u64>,
+    max_blocks: Option<u64>,
+    sample: Option<f64>,
+    seed: Option<u64>,
+    require_all_included: Option<bool>,
+    keep_comments: Option<bool>,
+    keep_completion_marker: Option<bool>,
+    keep_bom: Option<bool>,
+    comment_prefix: Option<Vec<String>>,
+    keep_blank_lines: Option<bool>,
+    strip_set: Option<bool>,
+    strip_search_path: Option<bool>,
+    strip_grants: Option<bool>,
+    strip_owner: Option<bool>,
+    exclude_statements: Option<Vec<String>>,
+    only_statements: Option<Vec<String>>,
+    keep_setval: Option<bool>,
+    drop_setval: Option<bool>,
+    schema_only: Option<bool>,
+    data_only: Option<bool>,
+    use_toc: Option<bool>,
+    tables_only_matching_data: Option<bool>,
+    strict: Option<bool>,
+    validate_rows: Option<bool>,
+    split_dir: Option<PathBuf>,
+    stream_rows_over: Option<usize>,
+    start_byte: Option<u64>,
+    end_byte: Option<u64>,
+    jobs: Option<usize>,
+    min_version: Option<String>,
+    drop_column: Option<Vec<String>>,
+    mask: Option<Vec<String>>,
+    mask_hash: Option<bool>,
+    mask_key: Option<String>,
+    where_equals: Option<Vec<String>>,
+    where_in: Option<Vec<String>>,
+    null_string: Option<String>,
+    to_inserts: Option<bool>,
+    to_jsonl: Option<bool>,
+    insert_batch: Option<usize>,
+    max_line_bytes: Option<usize>,
+    before_block: Option<String>,
+    after_block: Option<String>,
+    transform: Option<Vec<String>>,
+    truncate_table: Option<Vec<String>>,
+}
+
+impl ConfigFile {
+    fn read(path: &Path) -> Result<ConfigFile> {
+        let content =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(toml::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))?)
+    }
+
+    /// Fills in the options that were not given on the command line.
+    fn merge_into(self, opts: &mut Options, matches: &ArgMatches) -> Result<()> {
+        // structopt names the arguments after the fields in kebab case
+        let on_command_line = |field: &str| matches.occurrences_of(field.replace('_', "-")) > 0;
+        macro_rules! merge {
+            ($($field:ident),*) => {
+                $(if let Some(value) = self.$field {
+                    if !on_command_line(stringify!($field)) {
+                        opts.$field = value;
                     }
-                    _ => Ok(State::Statement),
-                }
+                })*
+            };
+        }
+        macro_rules! merge_optional {
+            ($($field:ident),*) => {
+                $(if self.$field.is_some() && !on_command_line(stringify!($field)) {
+                    opts.$field = self.$field;
+                })*
+            };
+        }
+        merge!(
+            excluded_copy_blocks,
+            included_copy_blocks,
+            exclude_large_objects,
+            truncate_large_objects,
+            exclude_lo_oid,
+            strict_lo,
+            input,
+            regex,
+            case_sensitive,
+            prefix_match,
+            allow_unqualified,
+            invert,
+            drop_column,
+            mask,
+            mask_hash,
+            where_equals,
+            where_in,
+            truncate_table,
+            lowercase_identifiers,
+            null_string,
+            to_inserts,
+            to_jsonl,
+            schema,
+            buffersize_in_mb,
+            read_buffer_kb,
+            write_buffer_kb,
+            stats,
+            progress,
+            timing,
+            quiet,
+            count_rows,
+            dry_run,
+            list,
+            summary_only,
+            require_all_included,
+            keep_comments,
+            keep_completion_marker,
+            keep_bom,
+            comment_prefix,
+            keep_blank_lines,
+            strip_set,
+            strip_search_path,
+            strip_grants,
+            strip_owner,
+            exclude_statements,
+            only_statements,
+            keep_setval,
+            drop_setval,
+            schema_only,
+            data_only,
+            use_toc,
+            tables_only_matching_data,
+            strict,
+            validate_rows
+        );
+        merge_optional!(
+            excluded_copy_blocks_file,
+            included_copy_blocks_file,
+            include_from_query_output,
+            stats_json,
+            manifest,
+            split_dir,
+            output,
+            max_rows,
+            max_blocks,
+            seed,
+            stream_rows_over,
+            start_byte,
+            end_byte,
+            jobs,
+            insert_batch,
+            max_line_bytes,
+            mask_key,
+            before_block,
+            after_block
+        );
+        if let Some(renames) = self.rename_schema {
+            if !on_command_line("rename_schema") {
+                opts.rename_schema = renames
+                    .iter()
+                    .map(|rename| parse_rename(rename))
+                    .collect::<std::result::Result<_, _>>()?;
             }
-            _ => match self {
-                State::ExcludedCopyBlock => Ok(State::ExcludedCopyBlock),
-                _ => Ok(State::Statement),
-            },
         }
+        if let Some(renames) = self.rename_table {
+            if !on_command_line("rename_table") {
+                opts.rename_table = renames
+                    .iter()
+                    .map(|rename| parse_rename(rename))
+                    .collect::<std::result::Result<_, _>>()?;
+            }
+        }
+        if let Some(transforms) = self.transform {
+            if !on_command_line("transform") {
+                opts.transform = transforms
+                    .iter()
+                    .map(|transform| parse_transform(transform))
+                    .collect::<std::result::Result<_, _>>()?;
+            }
+        }
+        if let Some(version) = self.min_version {
+            if !on_command_line("min_version") {
+                opts.min_version = Some(version.parse()?);
+            }
+        }
+        if let Some(compress) = self.compress {
+            if !on_command_line("compress") {
+                opts.compress = match compress {
+                    CompressSetting::Enabled(true) => Some(None),
+                    CompressSetting::Enabled(false) => None,
+                    CompressSetting::Codec(codec) => Some(Some(codec)),
+                };
+            }
+        }
+        if let Some(sample) = self.sample {
+            if !on_command_line("sample") {
+                opts.sample = Some(parse_fraction(&sample.to_string())?);
+            }
+        }
+        // the command line checks these conflicts, a config file could still combine them
+        if !opts.excluded_copy_blocks.is_empty() && !opts.included_copy_blocks.is_empty() {
+            return Err("excluded_copy_blocks and included_copy_blocks can not be combined".into());
+        }
+        if opts.exclude_large_objects && opts.truncate_large_objects {
+            return Err(
+                "exclude_large_objects and truncate_large_objects can not be combined".into(),
+            );
+        }
+        if opts.schema_only && opts.data_only {
+            return Err("schema_only and data_only can not be combined".into());
+        }
+        if opts.keep_setval && opts.drop_setval {
+            return Err("keep_setval and drop_setval can not be combined".into());
+        }
+        Ok(())
     }
+}
+
+fn parse_fraction(fraction: &str) -> std::result::Result<f64, String> {
+    match fraction.parse::<f64>() {
+        Ok(fraction) if (0.0..=1.0).contains(&fraction) => Ok(fraction),
+        _ => Err(format!(
+            "expected a number between 0 and 1, got '{}'",
+            fraction
+        )),
+    }
+}
 
-    fn must_include(&self, opts: &Options, prev_included_state: &State) -> bool {
+fn parse_positive(number: &str) -> std::result::Result<usize, String> {
+    match number.parse::<usize>() {
+        Ok(number) if number > 0 => Ok(number),
+        _ => Err(format!("expected a positive number, got '{}'", number)),
+    }
+}
+
+fn parse_transform(transform: &str) -> std::result::Result<(String, String), String> {
+    match transform.split_once('=') {
+        Some((table, command)) if !table.is_empty() && !command.trim().is_empty() => {
+            Ok((table.to_string(), command.to_string()))
+        }
+        _ => Err(format!(
+            "expected schema.table=command, got '{}'",
+            transform
+        )),
+    }
+}
+
+fn parse_rename(rename: &str) -> std::result::Result<(String, String), String> {
+    match rename.find('=') {
+        Some(i) if i > 0 && i < rename.len() - 1 => {
+            Ok((rename[..i].to_string(), rename[i + 1..].to_string()))
+        }
+        _ => Err(format!("expected old=new, got '{}'", rename)),
+    }
+}
+
+/// How the filtered dump is compressed.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Codec {
+    Gzip,
+    Zstd,
+}
+
+impl Codec {
+    /// The compression that goes with the suffix of the output file, e.g. zstd for `dump.sql.zst`.
+    fn of(path: &Path) -> Option<Codec> {
+        match path.extension().and_then(OsStr::to_str) {
+            Some("gz") => Some(Codec::Gzip),
+            Some("zst") => Some(Codec::Zstd),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Codec {
+    type Err = String;
+
+    fn from_str(codec: &str) -> std::result::Result<Codec, String> {
+        match codec {
+            "gzip" | "gz" => Ok(Codec::Gzip),
+            "zstd" | "zst" => Ok(Codec::Zstd),
+            _ => Err(format!("expected gzip or zstd, got '{}'", codec)),
+        }
+    }
+}
+
+/// The `compress` setting of a config file, `true` or the name of the compression.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum CompressSetting {
+    Enabled(bool),
+    Codec(Codec),
+}
+
+/// The destination of the filtered dump, optionally gzip or zstd compressed.
+enum Output<W: Write> {
+    Plain(W),
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> Output<W> {
+    /// Flushes the output, writing the gzip trailer or the end of the zstd frame if needed.
+    fn finish(self) -> io::Result<()> {
         match self {
-            State::Comment => false,
-            State::ConsecutiveEmptyLine => false,
-            State::ExcludedCopyBlock => false,
-            State::EndOfExcludedCopyBlock => false,
-            State::LargeObject if opts.exclude_large_objects => false,
-            State::EmptyLine if prev_included_state == &State::EmptyLine => false,
-            _ => true,
+            Output::Plain(mut writer) => writer.flush(),
+            Output::Gzip(encoder) => encoder.finish()?.flush(),
+            Output::Zstd(encoder) => encoder.finish()?.flush(),
         }
     }
 }
 
-pub fn main() -> Result<()> {
-    let opts: Options = Options::from_args();
-    let mut prev_included_state: State = State::Init;
-    let mut state: State = State::Init;
+impl<W: Write> Write for Output<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Plain(writer) => writer.write(buf),
+            Output::Gzip(encoder) => encoder.write(buf),
+            Output::Zstd(encoder) => encoder.write(buf),
+        }
+    }
 
-    let stdout = std::io::stdout();
-    let stdout = stdout.lock();
-    let mut stdout = BufWriter::with_capacity(64 * 1024, stdout);
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Plain(writer) => writer.flush(),
+            Output::Gzip(encoder) => encoder.flush(),
+            Output::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
 
-    let reader = io::stdin();
-    let reader = reader.lock();
-    let mut reader = BufReader::with_capacity(64 * 1024, reader);
-    let mut buf: Vec<u8> = Vec::with_capacity(opts.buffersize_in_mb * 1024 * 1024);
+/// The --output file under a temporary name next to it until it is complete, so a failed run
+/// never leaves a partial dump behind.  The temporary file is removed unless it is persisted.
+struct TempOutput {
+    temp: Option<PathBuf>,
+    path: PathBuf,
+}
 
-    loop {
-        buf.clear();
+impl TempOutput {
+    fn create(path: &Path) -> Result<(TempOutput, File)> {
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| format!("{}: not a file name", path.display()))?;
+        let mut temp_name = OsString::from(".");
+        temp_name.push(file_name);
+        temp_name.push(format!(".{}.tmp", std::process::id()));
+        let temp = path.with_file_name(temp_name);
+        let file = File::create(&temp).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let temp_output = TempOutput {
+            temp: Some(temp),
+            path: path.to_path_buf(),
+        };
+        Ok((temp_output, file))
+    }
 
-        let number_of_bytes_read = reader.read_until(b'\n', &mut buf)?;
-        if number_of_bytes_read == 0 {
-            break;
+    /// Renames the temporary file to the output file, replacing it if it exists.  The temporary
+    /// file must be closed by then, Windows does not rename open files.
+    fn persist(mut self) -> Result<()> {
+        if let Some(temp) = self.temp.take() {
+            fs::rename(&temp, &self.path).map_err(|e| format!("{}: {}", self.path.display(), e))?;
         }
-        state = state.next_state(&buf, &opts)?;
-        if state.must_include(&opts, &prev_included_state) {
-            prev_included_state = state;
-            stdout.write_all(&buf)?;
+        Ok(())
+    }
+}
+
+impl Drop for TempOutput {
+    fn drop(&mut self) {
+        if let Some(temp) = &self.temp {
+            // the run failed already, that error is the one to report
+            let _ = fs::remove_file(temp);
         }
     }
-    stdout.flush()?;
+}
+
+/// Buffers the input in a buffer of `buffer` bytes, decompressing it if it starts with the gzip or
+/// zstd magic bytes.
+fn decompressed(reader: Box<dyn Read>, buffer: usize) -> io::Result<Box<dyn BufRead>> {
+    let mut reader = BufReader::with_capacity(buffer, reader);
+    // peek at the magic bytes without consuming them, plain dumps go to the filter untouched
+    let magic = fill_buf(&mut reader)?;
+    Ok(if magic.starts_with(GZIP_MAGIC) {
+        Box::new(BufReader::with_capacity(
+            buffer,
+            MultiGzDecoder::new(reader),
+        ))
+    } else if magic.starts_with(ZSTD_MAGIC) {
+        Box::new(BufReader::with_capacity(
+            buffer,
+            zstd::Decoder::with_buffer(reader)?,
+        ))
+    } else {
+        Box::new(reader)
+    })
+}
+
+/// The dump from the --input files, or stdin without any.  Several inputs are read one after the
+/// other, as if they were one concatenated dump.
+fn input(
+    paths: &[PathBuf],
+    start: Option<u64>,
+    end: Option<u64>,
+    buffer: usize,
+) -> Result<Box<dyn BufRead>> {
+    if start.is_some() || end.is_some() {
+        return match paths {
+            [path] => Ok(part_of(path, start, end, buffer)
+                .map_err(|e| format!("{}: {}", path.display(), e))?),
+            _ => Err("--start-byte and --end-byte need a single --input file".into()),
+        };
+    }
+    if paths.is_empty() {
+        return Ok(decompressed(Box::new(io::stdin().lock()), buffer)?);
+    }
+    let mut readers = Vec::with_capacity(paths.len());
+    for path in paths {
+        let error = |e: io::Error| format!("{}: {}", path.display(), e);
+        readers
+            .push(decompressed(Box::new(File::open(path).map_err(error)?), buffer).map_err(error)?);
+    }
+    Ok(readers
+        .into_iter()
+        .reduce(|chained, reader| Box::new(chained.chain(reader)))
+        .expect("there is at least one input"))
+}
+
+/// The part of the dump in the file between `start` and `end` cut into `jobs` parts of about the
+/// same size, each between copy block boundaries.
+fn parts_of(
+    path: &Path,
+    start: Option<u64>,
+    end: Option<u64>,
+    jobs: usize,
+    buffer: usize,
+) -> Result<Vec<Box<dyn BufRead + Send>>> {
+    let start = start.unwrap_or(0);
+    let end = match end {
+        Some(end) => end,
+        None => std::fs::metadata(path)?.len(),
+    };
+    let size = end.saturating_sub(start);
+    let offset = |i: usize| start + size * i as u64 / jobs as u64;
+    (0..jobs)
+        .map(|i| part_of(path, Some(offset(i)), Some(offset(i + 1)), buffer))
+        .collect()
+}
+
+/// The part of the dump in the file between the copy block boundaries of `start` and `end`.
+fn part_of(
+    path: &Path,
+    start: Option<u64>,
+    end: Option<u64>,
+    buffer: usize,
+) -> Result<Box<dyn BufRead + Send>> {
+    let mut reader = BufReader::with_capacity(buffer, File::open(path)?);
+    // a compressed dump can not be read from the middle
+    let magic = fill_buf(&mut reader)?;
+    if magic.starts_with(GZIP_MAGIC) || magic.starts_with(ZSTD_MAGIC) {
+        return Err("--start-byte, --end-byte and --jobs need an uncompressed dump".into());
+    }
+    let end = match end {
+        Some(end) => copy_block_boundary(&mut reader, end)?,
+        None => reader.seek(SeekFrom::End(0))?,
+    };
+    let start = match start {
+        Some(start) => copy_block_boundary(&mut reader, start)?,
+        None => 0,
+    };
+    reader.seek(SeekFrom::Start(start))?;
+    Ok(Box::new(reader.take(end.saturating_sub(start))))
+}
+
+/// Reads a list of copy blocks, one per line.  Blank lines and lines starting with `#` are skipped.
+fn read_copy_blocks_file(path: &Path) -> Result<Vec<String>> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+/// Reads the copy blocks in the first column of the output of a psql query, either a table like
+/// ` name \n------\n foo\n bar\n(2 rows)` or a list without header and footer like `psql -At`
+/// writes.  The header is told by the ruler under it.
+fn read_query_output(path: &Path) -> Result<Vec<String>> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let lines: Vec<&str> = content.lines().collect();
+    let is_ruler = |line: &&str| {
+        let line = line.trim();
+        !line.is_empty() && line.chars().all(|c| c == '-' || c == '+')
+    };
+    let rows = match lines.iter().position(is_ruler) {
+        Some(ruler) => &lines[ruler + 1..],
+        None => &lines[..],
+    };
+    Ok(rows
+        .iter()
+        .map(|row| row.split('|').next().unwrap_or_default().trim())
+        .filter(|value| !value.is_empty() && !is_row_count(value))
+        .map(String::from)
+        .collect())
+}
+
+/// Whether the line is the footer of the output of a psql query, like `(42 rows)` or `(1 row)`.
+fn is_row_count(line: &str) -> bool {
+    let count = line.strip_prefix('(').and_then(|rest| {
+        rest.strip_suffix(" rows)")
+            .or_else(|| rest.strip_suffix(" row)"))
+    });
+    count.is_some_and(|count| !count.is_empty() && count.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// The time since `start` and the throughput, every line of the dump is counted as included or
+/// dropped once.
+fn timing(stats: &Stats, start: Instant) -> String {
+    let seconds = start.elapsed().as_secs_f64();
+    let bytes = stats.total_included().bytes + stats.total_dropped().bytes;
+    let mb = bytes as f64 / (1024.0 * 1024.0);
+    let throughput = if seconds > 0.0 { mb / seconds } else { 0.0 };
+    format!(
+        "{:.3} s, {} bytes read, {:.1} MB/s",
+        seconds, bytes, throughput
+    )
+}
+
+/// Which copy blocks a run includes and excludes, with the rows they have in the output or the
+/// dump respectively.
+fn dry_run_report(stats: &Stats) -> String {
+    let mut report = format!("{:<10}{:>16}  {}\n", "decision", "rows", "copy block");
+    for copy_block in &stats.copy_blocks {
+        let (decision, rows) = if copy_block.included {
+            ("included", copy_block.included_rows)
+        } else {
+            ("excluded", copy_block.rows)
+        };
+        report.push_str(&format!(
+            "{:<10}{:>16}  {}\n",
+            decision, rows, copy_block.name
+        ));
+    }
+    report
+}
+
+/// Every copy block in the dump with its rows and the columns of its header, in a table.
+fn summary(stats: &Stats) -> String {
+    let width = stats
+        .copy_blocks
+        .iter()
+        .map(|copy_block| copy_block.name.len())
+        .chain(Some("table".len()))
+        .max()
+        .unwrap_or_default();
+    let mut summary = format!(
+        "{:<width$}{:>16}  {}\n",
+        "table",
+        "rows",
+        "columns",
+        width = width
+    );
+    for copy_block in &stats.copy_blocks {
+        let line = format!(
+            "{:<width$}{:>16}  {}",
+            copy_block.name,
+            copy_block.rows,
+            copy_block.columns.join(", "),
+            width = width
+        );
+        // a copy block without a column list has no trailing spaces
+        summary.push_str(line.trim_end());
+        summary.push('\n');
+    }
+    summary
+}
+
+/// The rows in the output per included table as `schema.table: N` lines, sorted by table.
+fn row_counts(stats: &Stats) -> String {
+    let mut counts: BTreeMap<&str, u64> = BTreeMap::new();
+    for copy_block in stats
+        .copy_blocks
+        .iter()
+        .filter(|copy_block| copy_block.included)
+    {
+        *counts.entry(&copy_block.name).or_default() += copy_block.included_rows;
+    }
+    counts
+        .iter()
+        .map(|(name, rows)| format!("{}: {}\n", name, rows))
+        .collect()
+}
+
+/// Writes the value as JSON to the file, or to stderr for `-`.
+fn write_json(path: &Path, value: &impl Serialize) -> Result<()> {
+    let error = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+    let mut out: Box<dyn Write> = if path == Path::new("-") {
+        Box::new(io::stderr())
+    } else {
+        Box::new(File::create(path).map_err(|e| error(&e))?)
+    };
+    serde_json::to_writer_pretty(&mut out, value).map_err(|e| error(&e))?;
+    writeln!(out).map_err(|e| error(&e))?;
+    Ok(())
+}
+
+pub fn main() {
+    if let Err(error) = run() {
+        // the reader of the output, like `head`, has all it wants
+        if is_broken_pipe(&error) {
+            return;
+        }
+        // in red on a terminal, a log file gets the plain message
+        if io::stderr().is_terminal() {
+            eprintln!("\x1b[1;31merror:\x1b[0m {}", error);
+        } else {
+            eprintln!("error: {}", error);
+        }
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
+    let matches = Options::clap().get_matches();
+    let mut opts = Options::from_clap(&matches);
+    if let Some(path) = opts.config.clone() {
+        ConfigFile::read(&path)?.merge_into(&mut opts, &matches)?;
+    }
+    if opts.quiet {
+        opts.progress = false;
+        opts.stats = false;
+        opts.count_rows = false;
+        opts.timing = false;
+        if opts.stats_json.as_deref() == Some(Path::new("-")) {
+            opts.stats_json = None;
+        }
+        if opts.manifest.as_deref() == Some(Path::new("-")) {
+            opts.manifest = None;
+        }
+    } else {
+        // logs go to stderr, nothing is logged unless RUST_LOG asks for it
+        env_logger::init();
+    }
+    let mut config = FilterConfig {
+        excluded_copy_blocks: opts.excluded_copy_blocks,
+        included_copy_blocks: opts.included_copy_blocks,
+        exclude_large_objects: opts.exclude_large_objects,
+        regex: opts.regex,
+        schemas: opts.schema,
+        buffersize_in_mb: opts.buffersize_in_mb,
+        rename_schemas: opts.rename_schema,
+        rename_tables: opts.rename_table,
+        lowercase_identifiers: opts.lowercase_identifiers,
+        max_rows: opts.max_rows,
+        max_blocks: opts.max_blocks,
+        sample: opts.sample,
+        seed: opts.seed,
+        keep_comments: opts.keep_comments,
+        keep_completion_marker: opts.keep_completion_marker,
+        keep_bom: opts.keep_bom,
+        comment_prefixes: opts.comment_prefix.clone(),
+        keep_blank_lines: opts.keep_blank_lines,
+        strip_set: opts.strip_set,
+        strip_search_path: opts.strip_search_path,
+        strip_grants: opts.strip_grants,
+        strip_owner: opts.strip_owner,
+        excluded_statements: opts.exclude_statements.clone(),
+        only_statements: opts.only_statements.clone(),
+        keep_setval: opts.keep_setval,
+        drop_setval: opts.drop_setval,
+        schema_only: opts.schema_only,
+        data_only: opts.data_only,
+        use_toc: opts.use_toc,
+        tables_only_matching_data: opts.tables_only_matching_data,
+        strict: opts.strict,
+        validate_rows: opts.validate_rows,
+        truncate_large_objects: opts.truncate_large_objects,
+        excluded_large_objects: opts.exclude_lo_oid,
+        strict_large_objects: opts.strict_lo,
+        progress: opts.progress,
+        split_dir: opts.split_dir.clone(),
+        stream_rows_over: opts.stream_rows_over,
+        case_sensitive: opts.case_sensitive,
+        prefix_match: opts.prefix_match,
+        allow_unqualified: opts.allow_unqualified,
+        invert: opts.invert,
+        min_version: opts.min_version.clone(),
+        drop_columns: opts.drop_column.clone(),
+        mask_columns: opts.mask.clone(),
+        mask_hash: opts.mask_hash,
+        mask_key: opts.mask_key.clone(),
+        where_equals: opts.where_equals.clone(),
+        where_in: opts.where_in.clone(),
+        null_string: opts.null_string.clone(),
+        to_inserts: opts.to_inserts,
+        to_jsonl: opts.to_jsonl,
+        insert_batch: opts.insert_batch.unwrap_or(1),
+        max_line_bytes: opts.max_line_bytes,
+        before_block: opts.before_block.clone(),
+        after_block: opts.after_block.clone(),
+        transforms: opts.transform.clone(),
+        truncated_tables: opts.truncate_table,
+    };
+    if opts.dry_run {
+        config.split_dir = None;
+        config.transforms.clear();
+    }
+    if let Some(path) = &opts.excluded_copy_blocks_file {
+        let blocks = read_copy_blocks_file(path)?;
+        config.excluded_copy_blocks.extend(blocks);
+    }
+    if let Some(path) = &opts.included_copy_blocks_file {
+        let blocks = read_copy_blocks_file(path)?;
+        config.included_copy_blocks.extend(blocks);
+    }
+    if let Some(path) = &opts.include_from_query_output {
+        let blocks = read_query_output(path)?;
+        config.included_copy_blocks.extend(blocks);
+    }
+
+    let stdout = io::stdout();
+    let mut temp_output = None;
+    let writer: Box<dyn Write> = match &opts.output {
+        _ if opts.dry_run => Box::new(io::sink()),
+        Some(path) => {
+            let (temp, file) = TempOutput::create(path)?;
+            temp_output = Some(temp);
+            Box::new(file)
+        }
+        None => Box::new(stdout.lock()),
+    };
+    let read_buffer = opts.read_buffer_kb * 1024;
+    let writer = BufWriter::with_capacity(opts.write_buffer_kb * 1024, writer);
+    let codec_of_output = opts.output.as_deref().and_then(Codec::of);
+    let codec = match opts.compress {
+        Some(Some(codec)) => Some(codec),
+        Some(None) => Some(codec_of_output.unwrap_or(Codec::Gzip)),
+        None => codec_of_output,
+    };
+    let mut writer = match codec {
+        Some(Codec::Gzip) => Output::Gzip(GzEncoder::new(writer, Compression::default())),
+        Some(Codec::Zstd) => Output::Zstd(zstd::Encoder::new(writer, 0)?),
+        None => Output::Plain(writer),
+    };
+
+    if opts.list || opts.summary_only {
+        let stats = filter(
+            input(&opts.input, opts.start_byte, opts.end_byte, read_buffer)?,
+            io::sink(),
+            &FilterConfig::default(),
+        )?;
+        if opts.summary_only {
+            write!(writer, "{}", summary(&stats))?;
+        } else {
+            for copy_block in stats.copy_blocks {
+                writeln!(writer, "{}", copy_block.name)?;
+            }
+        }
+        writer.finish()?;
+        if let Some(temp_output) = temp_output {
+            temp_output.persist()?;
+        }
+        return Ok(());
+    }
+
+    let start = Instant::now();
+    let stats = match opts.jobs {
+        Some(jobs) if jobs > 1 => match &opts.input[..] {
+            [path] => {
+                let parts = parts_of(path, opts.start_byte, opts.end_byte, jobs, read_buffer)
+                    .map_err(|e| format!("{}: {}", path.display(), e))?;
+                filter_parts(parts, &mut writer, &config)?
+            }
+            _ => return Err("--jobs needs a single --input file".into()),
+        },
+        _ => {
+            let reader = input(&opts.input, opts.start_byte, opts.end_byte, read_buffer)?;
+            filter(reader, &mut writer, &config)?
+        }
+    };
+    writer.finish()?;
+    if let Some(temp_output) = temp_output {
+        temp_output.persist()?;
+    }
+    if !stats.unmatched_schemas.is_empty() && !opts.quiet {
+        eprintln!(
+            "warning: no copy block in the dump is in schema(s) {}",
+            stats.unmatched_schemas.join(", ")
+        );
+    }
+    if opts.dry_run && !opts.quiet {
+        eprint!("{}", dry_run_report(&stats));
+    }
+    if opts.count_rows {
+        eprint!("{}", row_counts(&stats));
+    }
+    if opts.strict_lo && !opts.quiet {
+        for (function, calls) in &stats.large_object_functions {
+            eprintln!("{}: {}", function, calls);
+        }
+    }
+    if opts.stats {
+        eprint!("{}", stats);
+    }
+    if opts.timing {
+        eprintln!("{}", timing(&stats, start));
+    }
+    if let Some(path) = &opts.stats_json {
+        write_json(path, &stats)?;
+    }
+    if let Some(path) = &opts.manifest {
+        write_json(path, &stats.manifest())?;
+    }
+    if opts.require_all_included && !stats.unmatched_included_copy_blocks.is_empty() {
+        return Err(format!(
+            "included copy block(s) not found in the dump: {}",
+            stats.unmatched_included_copy_blocks.join(", ")
+        )
+        .into());
+    }
     Ok(())
 }
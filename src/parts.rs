@@ -0,0 +1,167 @@
+//! Filtering the parts of a dump between copy block boundaries on threads of their own.
+
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread;
+
+use crate::{is_broken_pipe, Count, Filter, FilterConfig, Result, State, Stats};
+
+/// Size of the pieces of output sent from a part's thread to the writer.
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Number of pieces a part can get ahead of the writer, the threads of the later parts wait
+/// until the writer gets to them.
+const CHUNKS_AHEAD: usize = 64;
+
+/// Filters the parts of a dump, each starting at a copy block boundary, on a thread per part and
+/// writes their outputs to `writer` in order.  The output and the statistics are those of filtering
+/// the parts one after the other, as a part always starts outside of a copy block.
+///
+/// The line numbers in an error count from the start of its part.  `split_dir`, `max_blocks` and
+/// `progress` need the whole dump and can not be combined with it, and `sample` picks other rows.
+pub fn filter_parts<R, W>(parts: Vec<R>, mut writer: W, config: &FilterConfig) -> Result<Stats>
+where
+    R: BufRead + Send,
+    W: Write,
+{
+    if config.split_dir.is_some() || config.max_blocks.is_some() || config.progress {
+        return Err("split_dir, max_blocks and progress need the dump filtered as a whole".into());
+    }
+    // the version is in the comments at the top, in the first part only
+    // the parts hash the masked values with the same key
+    let config = &FilterConfig {
+        mask_key: config
+            .mask_key
+            .clone()
+            .or_else(|| config.mask_hash.then(crate::columns::random_mask_key)),
+        ..config.clone()
+    };
+    let rest_config = FilterConfig {
+        min_version: None,
+        ..config.clone()
+    };
+    let stats = thread::scope(|scope| -> Result<Vec<Stats>> {
+        let mut outputs = Vec::with_capacity(parts.len());
+        for (i, part) in parts.into_iter().enumerate() {
+            let config = if i == 0 { config } else { &rest_config };
+            let (sender, receiver) = mpsc::sync_channel(CHUNKS_AHEAD);
+            let filtering = scope.spawn(move || filter_part(part, sender, config));
+            outputs.push((receiver, filtering));
+        }
+        let mut stats = Vec::with_capacity(outputs.len());
+        let mut last_included = State::Init;
+        for (i, (receiver, filtering)) in outputs.into_iter().enumerate() {
+            // an empty line right after one in the part before is collapsed into it
+            let skip_empty_line = last_included == State::EmptyLine && !config.keep_blank_lines;
+            let skipped = write_pieces(&receiver, &mut writer, skip_empty_line)?;
+            let (mut part_stats, part_last_included) = filtering
+                .join()
+                .map_err(|_| format!("part {}: the filtering panicked", i + 1))?
+                .map_err(|e| {
+                    if is_broken_pipe(&e) {
+                        e
+                    } else {
+                        format!("part {}: {}", i + 1, e).into()
+                    }
+                })?;
+            if let Some(bytes) = skipped {
+                let count = Count { lines: 1, bytes };
+                part_stats.uncount(State::EmptyLine, count, true);
+                part_stats.count(State::EmptyLine, bytes, false);
+            }
+            if part_last_included != State::Init {
+                last_included = part_last_included;
+            }
+            stats.push(part_stats);
+        }
+        Ok(stats)
+    })?;
+    writer.flush()?;
+    Ok(Stats::merge(stats))
+}
+
+/// A piece of the output of a part.
+enum Piece {
+    Chunk(Vec<u8>),
+    /// An empty line included before any other line of the part, which the part before decides
+    /// on: it is dropped after an included empty line
+    LeadingEmptyLine(Vec<u8>),
+}
+
+/// Filters a part, returning its statistics and the state of its last included line.
+fn filter_part<R: BufRead>(
+    reader: R,
+    sender: SyncSender<Piece>,
+    config: &FilterConfig,
+) -> Result<(Stats, State)> {
+    let mut writer = ChannelWriter {
+        chunk: Vec::with_capacity(CHUNK_SIZE),
+        sender,
+    };
+    let mut filter = Filter::new(reader, config)?;
+    while filter.next_line(&mut writer)? {
+        if filter.prev_included_state != State::Init {
+            break;
+        }
+    }
+    // nothing was written before the first included line, so the chunk is that line
+    if filter.prev_included_state == State::EmptyLine {
+        let line = std::mem::take(&mut writer.chunk);
+        writer.send(Piece::LeadingEmptyLine(line))?;
+    }
+    while filter.next_line(&mut writer)? {}
+    writer.flush()?;
+    let last_included = filter.prev_included_state;
+    Ok((filter.finish()?, last_included))
+}
+
+/// Writes the pieces of a part's output, leaving out its leading empty line if `skip_empty_line`.
+/// Returns the size of the line left out.
+fn write_pieces<W: Write>(
+    receiver: &Receiver<Piece>,
+    writer: &mut W,
+    skip_empty_line: bool,
+) -> io::Result<Option<u64>> {
+    let mut skipped = None;
+    // the channel closes once the part is filtered or failed
+    for piece in receiver {
+        match piece {
+            Piece::Chunk(chunk) => writer.write_all(&chunk)?,
+            Piece::LeadingEmptyLine(line) if skip_empty_line => skipped = Some(line.len() as u64),
+            Piece::LeadingEmptyLine(line) => writer.write_all(&line)?,
+        }
+    }
+    Ok(skipped)
+}
+
+/// Writes to a channel in pieces of about [`CHUNK_SIZE`] bytes.
+struct ChannelWriter {
+    chunk: Vec<u8>,
+    sender: SyncSender<Piece>,
+}
+
+impl ChannelWriter {
+    fn send(&self, piece: Piece) -> io::Result<()> {
+        // the writer only goes away after an error of its own, which is the one reported
+        self.sender
+            .send(piece)
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "the output was closed"))
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.chunk.extend_from_slice(buf);
+        if self.chunk.len() >= CHUNK_SIZE {
+            self.flush()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.chunk.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::replace(&mut self.chunk, Vec::with_capacity(CHUNK_SIZE));
+        self.send(Piece::Chunk(chunk))
+    }
+}
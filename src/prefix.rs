@@ -0,0 +1,67 @@
+use std::sync::OnceLock;
+
+use aho_corasick::{AhoCorasick, AhoCorasickKind, Anchored, Input, MatchKind, StartKind};
+
+use crate::{
+    ALTER_SEQUENCE, ALTER_TABLE, COPY_BLOCK_PREFIX, CRLF, DDL, END_OF_COPY_BLOCK, GRANT, LO_CREATE,
+    LO_FN, LO_WRITE, NEWLINE, REVOKE, SET, SETVAL, SET_CONFIG_SEARCH_PATH, SET_SEARCH_PATH,
+};
+
+/// The kinds of lines told apart by how they start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prefix {
+    LargeObjectCreate,
+    LargeObject,
+    LargeObjectWrite,
+    EmptyLine,
+    SearchPath,
+    Set,
+    Grant,
+    SetVal,
+    /// `ALTER TABLE` or `ALTER SEQUENCE`, an owner change if followed by `OWNER TO`
+    AlterTableOrSequence,
+    EndOfCopyBlock,
+    CopyBlock,
+    Ddl,
+}
+
+fn prefixes() -> Vec<(&'static [u8], Prefix)> {
+    let mut prefixes = vec![
+        (LO_CREATE, Prefix::LargeObjectCreate),
+        (LO_FN, Prefix::LargeObject),
+        (LO_WRITE, Prefix::LargeObjectWrite),
+        (NEWLINE, Prefix::EmptyLine),
+        (CRLF, Prefix::EmptyLine),
+        (SET_SEARCH_PATH, Prefix::SearchPath),
+        (SET_CONFIG_SEARCH_PATH, Prefix::SearchPath),
+        (SET, Prefix::Set),
+        (GRANT, Prefix::Grant),
+        (REVOKE, Prefix::Grant),
+        (SETVAL, Prefix::SetVal),
+        (ALTER_TABLE, Prefix::AlterTableOrSequence),
+        (ALTER_SEQUENCE, Prefix::AlterTableOrSequence),
+        (END_OF_COPY_BLOCK, Prefix::EndOfCopyBlock),
+        (COPY_BLOCK_PREFIX, Prefix::CopyBlock),
+    ];
+    prefixes.extend(DDL.iter().map(|&ddl| (ddl, Prefix::Ddl)));
+    prefixes
+}
+
+/// The kind of line `buf` starts with, the longest prefix wins, e.g. `SET search_path ` over
+/// `SET `.  All prefixes are looked for in a single pass over the start of the line.
+pub fn prefix(buf: &[u8]) -> Option<Prefix> {
+    static MATCHER: OnceLock<(AhoCorasick, Vec<Prefix>)> = OnceLock::new();
+    let (automaton, kinds) = MATCHER.get_or_init(|| {
+        let (patterns, kinds): (Vec<_>, Vec<_>) = prefixes().into_iter().unzip();
+        let automaton = AhoCorasick::builder()
+            .match_kind(MatchKind::LeftmostLongest)
+            .start_kind(StartKind::Anchored)
+            .kind(Some(AhoCorasickKind::DFA))
+            .build(patterns)
+            .expect("the prefixes are valid patterns");
+        (automaton, kinds)
+    });
+    automaton
+        .find(Input::new(buf).anchored(Anchored::Yes))
+        .map(|m| kinds[m.pattern().as_usize()])
+}
@@ -0,0 +1,47 @@
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// How often the progress line is refreshed.
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// Reports the number of bytes processed so far on stderr, updating a single line in place.
+pub(crate) struct Progress {
+    start: Instant,
+    last_report: Instant,
+    bytes: u64,
+}
+
+impl Progress {
+    pub(crate) fn new() -> Progress {
+        let now = Instant::now();
+        Progress {
+            start: now,
+            last_report: now,
+            bytes: 0,
+        }
+    }
+
+    /// Adds the bytes just read, printing the progress line if the interval has passed.
+    pub(crate) fn add(&mut self, bytes: u64) {
+        self.bytes += bytes;
+        if self.last_report.elapsed() >= INTERVAL {
+            self.last_report = Instant::now();
+            self.report();
+        }
+    }
+
+    /// Prints the progress line one last time and moves to the next line.
+    pub(crate) fn finish(&self) {
+        self.report();
+        eprintln!();
+    }
+
+    fn report(&self) {
+        let seconds = self.start.elapsed().as_secs_f64();
+        let mb = self.bytes as f64 / (1024.0 * 1024.0);
+        let throughput = if seconds > 0.0 { mb / seconds } else { 0.0 };
+        eprint!("\r{:.1} MB processed, {:.1} MB/s", mb, throughput);
+        // a failing stderr must not fail the filtering
+        let _ = io::stderr().flush();
+    }
+}
@@ -0,0 +1,238 @@
+//! Rewriting the names in the statements and copy block headers: the renames of schemas and
+//! tables and the folding of the names to lowercase.
+
+/// Rewrites schema qualified identifiers in a statement, e.g. with the rename `public=tenant`
+/// `ALTER TABLE ONLY public.foo` becomes `ALTER TABLE ONLY tenant.foo`.  Only names directly
+/// followed by a `.` are rewritten, both in their unquoted (case insensitive) and quoted form.
+pub(crate) fn rename_schemas(line: &[u8], renames: &[(String, String)]) -> Vec<u8> {
+    let mut renamed = Vec::with_capacity(line.len());
+    let mut i = 0;
+    while i < line.len() {
+        if i == 0 || !is_qualified_name_byte(line[i - 1]) {
+            let rename = renames.iter().find_map(|(old, new)| {
+                qualifier_len(&line[i..], old.as_bytes()).map(|len| (len, new))
+            });
+            if let Some((len, new)) = rename {
+                renamed.extend_from_slice(new.as_bytes());
+                renamed.push(b'.');
+                i += len;
+                continue;
+            }
+        }
+        renamed.push(line[i]);
+        i += 1;
+    }
+    renamed
+}
+
+/// Rewrites schema qualified table names in a statement, e.g. with the rename `public.foo=bar`
+/// `ALTER TABLE ONLY public.foo` becomes `ALTER TABLE ONLY public.bar`.  The schema stays as it
+/// is written, the table is matched like the schema in its unquoted and quoted form.
+pub(crate) fn rename_tables(line: &[u8], renames: &[(String, String)]) -> Vec<u8> {
+    let renames: Vec<_> = renames
+        .iter()
+        .filter_map(|(old, new)| {
+            let (schema, table) = crate::header::split_qualified_name(old)?;
+            Some((schema, table, new))
+        })
+        .collect();
+    let mut renamed = Vec::with_capacity(line.len());
+    let mut i = 0;
+    while i < line.len() {
+        if i == 0 || !is_qualified_name_byte(line[i - 1]) {
+            let rename = renames.iter().find_map(|(schema, table, new)| {
+                let schema_len = qualifier_len(&line[i..], schema.as_bytes())?;
+                let table_len = word_len(&line[i + schema_len..], table.as_bytes())?;
+                Some((schema_len, table_len, new))
+            });
+            if let Some((schema_len, table_len, new)) = rename {
+                renamed.extend_from_slice(&line[i..i + schema_len]);
+                renamed.extend_from_slice(new.as_bytes());
+                i += schema_len + table_len;
+                continue;
+            }
+        }
+        renamed.push(line[i]);
+        i += 1;
+    }
+    renamed
+}
+
+/// Rewrites the schemas in a statement setting the search path, e.g. with the rename
+/// `public=tenant` `SET search_path = public, pg_catalog;` becomes
+/// `SET search_path = tenant, pg_catalog;` and likewise for the value of a
+/// `SELECT pg_catalog.set_config('search_path', 'public', false);`.
+pub(crate) fn rename_search_path(line: &[u8], renames: &[(String, String)]) -> Vec<u8> {
+    // the schemas are the words after the setting's name, `pg_catalog.set_config` stays as is
+    let start = crate::find(line, b"search_path")
+        .map(|i| i + b"search_path".len())
+        .unwrap_or(line.len());
+    let mut renamed = line[..start].to_vec();
+    let mut i = start;
+    while i < line.len() {
+        if i == start || !is_word_byte(line[i - 1]) {
+            let rename = renames
+                .iter()
+                .find_map(|(old, new)| word_len(&line[i..], old.as_bytes()).map(|len| (len, new)));
+            if let Some((len, new)) = rename {
+                renamed.extend_from_slice(new.as_bytes());
+                i += len;
+                continue;
+            }
+        }
+        renamed.push(line[i]);
+        i += 1;
+    }
+    renamed
+}
+
+/// Length of the word `schema` or `"schema"` at the start of `rest`, if present.
+fn word_len(rest: &[u8], schema: &[u8]) -> Option<usize> {
+    let n = schema.len();
+    let is_end = |len: usize| !rest.get(len).is_some_and(|&b| is_word_byte(b));
+    if rest.len() >= n && rest[..n].eq_ignore_ascii_case(schema) && is_end(n) {
+        Some(n)
+    } else if rest.len() >= n + 2
+        && rest[0] == b'"'
+        && &rest[1..n + 1] == schema
+        && rest[n + 1] == b'"'
+    {
+        Some(n + 2)
+    } else {
+        None
+    }
+}
+
+fn is_word_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || b >= 0x80
+}
+
+/// Length of `schema.` or `"schema".` at the start of `rest`, if present.
+fn qualifier_len(rest: &[u8], schema: &[u8]) -> Option<usize> {
+    let n = schema.len();
+    if rest.len() > n && rest[..n].eq_ignore_ascii_case(schema) && rest[n] == b'.' {
+        Some(n + 1)
+    } else if rest.len() > n + 2
+        && rest[0] == b'"'
+        && &rest[1..n + 1] == schema
+        && rest[n + 1] == b'"'
+        && rest[n + 2] == b'.'
+    {
+        Some(n + 3)
+    } else {
+        None
+    }
+}
+
+/// Whether the byte can be part of a quoted or schema qualified name, i.e. a name does not start
+/// right after it.
+fn is_qualified_name_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || b == b'"' || b == b'.' || b >= 0x80
+}
+
+/// Folds the identifiers in a statement to lowercase, e.g. `ALTER TABLE ONLY "Sales"."Order"`
+/// becomes `ALTER TABLE ONLY "sales"."order"` and `CREATE SCHEMA "Sales"` becomes
+/// `CREATE SCHEMA "sales"`.  Quoted names keep their quotes, unquoted names that are not schema
+/// qualified are left alone as they may be keywords, which postgres folds anyway.  A string
+/// literal is folded only when it names a sequence or table like the `'public."Foo_id_seq"'` of
+/// a `setval` or a `'public."Foo_id_seq"'::regclass`.
+pub(crate) fn lowercase_identifiers(line: &[u8]) -> Vec<u8> {
+    let mut folded = Vec::with_capacity(line.len());
+    let mut i = 0;
+    while i < line.len() {
+        if line[i] == b'\'' {
+            let end = string_end(&line[i..]);
+            let is_closed = end > 1 && line[i + end - 1] == b'\'';
+            let content = &line[i + 1..i + end - usize::from(is_closed)];
+            let is_name =
+                line[i + end..].starts_with(b"::regclass") || folded.ends_with(b"setval(");
+            let is_whole_name =
+                qualified_name_len(content).is_some_and(|(len, _)| len == content.len());
+            if is_closed && is_name && is_whole_name {
+                folded.extend_from_slice(&line[i..i + end].to_ascii_lowercase());
+            } else {
+                folded.extend_from_slice(&line[i..i + end]);
+            }
+            i += end;
+            continue;
+        }
+        if i == 0 || !is_qualified_name_byte(line[i - 1]) {
+            if let Some((len, is_qualified)) = qualified_name_len(&line[i..]) {
+                if is_qualified || line[i] == b'"' {
+                    folded.extend_from_slice(&line[i..i + len].to_ascii_lowercase());
+                } else {
+                    folded.extend_from_slice(&line[i..i + len]);
+                }
+                i += len;
+                continue;
+            }
+        }
+        folded.push(line[i]);
+        i += 1;
+    }
+    folded
+}
+
+/// Folds the schemas in a statement setting the search path to lowercase, quoted ones too, like
+/// [`lowercase_identifiers`] does with the qualified names.
+pub(crate) fn lowercase_search_path(line: &[u8]) -> Vec<u8> {
+    let start = crate::find(line, b"search_path")
+        .map(|i| i + b"search_path".len())
+        .unwrap_or(line.len());
+    let mut folded = line[..start].to_vec();
+    folded.extend_from_slice(&line[start..].to_ascii_lowercase());
+    folded
+}
+
+/// Length of the string literal at the start of `rest` up to and including its closing quote, a
+/// `''` standing for a quote in it, or up to the end of the line if it is not closed on it.
+fn string_end(rest: &[u8]) -> usize {
+    let mut i = 1;
+    while i < rest.len() {
+        if rest[i] == b'\'' {
+            if rest.get(i + 1) == Some(&b'\'') {
+                i += 2;
+                continue;
+            }
+            return i + 1;
+        }
+        i += 1;
+    }
+    rest.len()
+}
+
+/// Length of the dotted name like `public."Foo".id` at the start of `rest`, if it starts with an
+/// identifier, and whether it has more than one part.
+fn qualified_name_len(rest: &[u8]) -> Option<(usize, bool)> {
+    let mut len = identifier_len(rest)?;
+    let mut parts = 1;
+    while rest.get(len) == Some(&b'.') {
+        match identifier_len(&rest[len + 1..]) {
+            Some(next) => {
+                len += 1 + next;
+                parts += 1;
+            }
+            None => break,
+        }
+    }
+    Some((len, parts > 1))
+}
+
+/// Length of the quoted or unquoted identifier at the start of `rest`, if it starts with one.
+fn identifier_len(rest: &[u8]) -> Option<usize> {
+    match rest.first()? {
+        b'"' => {
+            let mut i = 1;
+            loop {
+                match rest.get(i)? {
+                    // a `""` stands for a `"` in the identifier
+                    b'"' if rest.get(i + 1) == Some(&b'"') => i += 2,
+                    b'"' => return Some(i + 1),
+                    _ => i += 1,
+                }
+            }
+        }
+        b if b.is_ascii_digit() || !is_word_byte(*b) => None,
+        _ => Some(rest.iter().take_while(|&&b| is_word_byte(b)).count()),
+    }
+}
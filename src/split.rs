@@ -0,0 +1,89 @@
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{Result, State};
+
+/// Writes every included copy block to its own `schema.table.sql` file in a directory, and all
+/// the other included lines to `schema.sql` in that directory.
+pub(crate) struct Split {
+    dir: PathBuf,
+    preamble: BufWriter<File>,
+    copy_block: Option<BufWriter<File>>,
+    /// The copy block files written so far, a table with several copy blocks appends to its file
+    written: HashSet<PathBuf>,
+}
+
+impl Split {
+    pub(crate) fn new(dir: &Path) -> Result<Split> {
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        Ok(Split {
+            dir: dir.to_path_buf(),
+            preamble: create(&dir.join("schema.sql"))?,
+            copy_block: None,
+            written: HashSet::new(),
+        })
+    }
+
+    /// Sends the lines from the header on to the file of the copy block `name`, e.g.
+    /// `public."user"` goes to `public.user.sql`.  The copy blocks of a table after its first one
+    /// are appended to the same file.
+    pub(crate) fn start_copy_block(&mut self, name: &str) -> Result<()> {
+        self.end_copy_block()?;
+        let file_name: String = name
+            .chars()
+            .filter(|&c| c != '"')
+            .map(|c| if c == '/' || c == '\0' { '_' } else { c })
+            .collect();
+        let path = self.dir.join(format!("{}.sql", file_name));
+        self.copy_block = Some(if self.written.contains(&path) {
+            append(&path)?
+        } else {
+            create(&path)?
+        });
+        self.written.insert(path);
+        Ok(())
+    }
+
+    /// The file a line in this state goes to, the copy block ends at the first line that is no
+    /// longer part of it.
+    pub(crate) fn writer(&mut self, state: State) -> Result<&mut dyn Write> {
+        if !matches!(
+            state,
+            State::IncludedCopyBlock | State::EndOfIncludedCopyBlock
+        ) {
+            self.end_copy_block()?;
+        }
+        Ok(match &mut self.copy_block {
+            Some(copy_block) => copy_block,
+            None => &mut self.preamble,
+        })
+    }
+
+    pub(crate) fn finish(&mut self) -> Result<()> {
+        self.end_copy_block()?;
+        self.preamble.flush()?;
+        Ok(())
+    }
+
+    fn end_copy_block(&mut self) -> Result<()> {
+        if let Some(mut copy_block) = self.copy_block.take() {
+            copy_block.flush()?;
+        }
+        Ok(())
+    }
+}
+
+fn create(path: &Path) -> Result<BufWriter<File>> {
+    let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(BufWriter::new(file))
+}
+
+fn append(path: &Path) -> Result<BufWriter<File>> {
+    let file = OpenOptions::new()
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(BufWriter::new(file))
+}
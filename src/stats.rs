@@ -0,0 +1,217 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::{header, State};
+
+/// Number of lines and bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Count {
+    pub lines: u64,
+    pub bytes: u64,
+}
+
+impl Count {
+    fn add(&mut self, bytes: u64) {
+        self.lines += 1;
+        self.bytes += bytes;
+    }
+
+    fn merge(&mut self, other: Count) {
+        self.lines += other.lines;
+        self.bytes += other.bytes;
+    }
+}
+
+/// A copy block encountered in the dump.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CopyBlockStats {
+    /// Table name as it appears in the `COPY` header, e.g. `public."user"`
+    pub name: String,
+    /// Columns of the column list of the `COPY` header as they appear in it, empty without one
+    pub columns: Vec<String>,
+    pub included: bool,
+    /// Number of data rows in the dump, including the ones that were dropped
+    pub rows: u64,
+    /// Number of data rows written to the output
+    pub included_rows: u64,
+    /// Size of the data rows in the dump, including the ones that were dropped
+    pub bytes: u64,
+    /// Size of the data rows written to the output
+    pub included_bytes: u64,
+    /// Why the copy block was included or excluded
+    pub reason: Reason,
+}
+
+/// Why a copy block was included or excluded, in the order they are checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+    SchemaOnly,
+    LargeObjectData,
+    Excluded,
+    NotIncluded,
+    /// Would be included, but `max_blocks` copy blocks already were
+    MaxBlocks,
+    Included,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Reason::SchemaOnly => "schema only",
+            Reason::LargeObjectData => "large object data",
+            Reason::Excluded => "matches an excluded copy block",
+            Reason::NotIncluded => "matches no included copy block",
+            Reason::MaxBlocks => "over the maximum number of copy blocks",
+            Reason::Included => "not excluded",
+        })
+    }
+}
+
+/// A table of the dump as listed in the manifest, see [`Stats::manifest`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ManifestEntry {
+    /// Schema without its quotes, `None` for a table name without a schema
+    pub schema: Option<String>,
+    /// Table name without its quotes
+    pub table: String,
+    pub included: bool,
+    pub reason: Reason,
+    pub rows: u64,
+    pub included_rows: u64,
+}
+
+/// What happened to the lines of a dump, per [`State`] they were in.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Stats {
+    pub included: BTreeMap<State, Count>,
+    pub dropped: BTreeMap<State, Count>,
+    /// Every copy block in the order of the dump
+    pub copy_blocks: Vec<CopyBlockStats>,
+    /// Included copy blocks that did not match any copy block in the dump
+    pub unmatched_included_copy_blocks: Vec<String>,
+    /// Schemas of the included and excluded copy blocks without any copy block in the dump
+    pub unmatched_schemas: Vec<String>,
+    /// Number of calls per large object function, e.g. `lo_open`
+    pub large_object_functions: BTreeMap<String, u64>,
+}
+
+impl Stats {
+    pub(crate) fn count(&mut self, state: State, bytes: u64, included: bool) {
+        let counts = if included {
+            &mut self.included
+        } else {
+            &mut self.dropped
+        };
+        counts.entry(state).or_default().add(bytes);
+    }
+
+    /// Takes back lines counted in a state, e.g. when they turned out to be dropped after all.
+    pub(crate) fn uncount(&mut self, state: State, count: Count, included: bool) {
+        let counts = if included {
+            &mut self.included
+        } else {
+            &mut self.dropped
+        };
+        if let Some(counted) = counts.get_mut(&state) {
+            counted.lines -= count.lines;
+            counted.bytes -= count.bytes;
+        }
+    }
+
+    /// The statistics of the parts of a dump put together, as if it was filtered as a whole.  A
+    /// copy block or schema is unmatched if it is in none of the parts.
+    pub(crate) fn merge(parts: Vec<Stats>) -> Stats {
+        let mut parts = parts.into_iter();
+        let mut merged = parts.next().unwrap_or_default();
+        for part in parts {
+            for (state, count) in part.included {
+                merged.included.entry(state).or_default().merge(count);
+            }
+            for (state, count) in part.dropped {
+                merged.dropped.entry(state).or_default().merge(count);
+            }
+            merged.copy_blocks.extend(part.copy_blocks);
+            let (unmatched_blocks, unmatched_schemas) =
+                (part.unmatched_included_copy_blocks, part.unmatched_schemas);
+            merged
+                .unmatched_included_copy_blocks
+                .retain(|block| unmatched_blocks.contains(block));
+            merged
+                .unmatched_schemas
+                .retain(|schema| unmatched_schemas.contains(schema));
+            for (function, calls) in part.large_object_functions {
+                *merged.large_object_functions.entry(function).or_default() += calls;
+            }
+        }
+        merged
+    }
+
+    /// Every copy block of the dump with what happened to it, for auditing what was left out.
+    pub fn manifest(&self) -> Vec<ManifestEntry> {
+        self.copy_blocks
+            .iter()
+            .map(|copy_block| {
+                let (schema, table) = match header::split_qualified_name(&copy_block.name) {
+                    Some((schema, table)) => (Some(schema.into_owned()), table.into_owned()),
+                    None => (None, header::unquote(&copy_block.name).into_owned()),
+                };
+                ManifestEntry {
+                    schema,
+                    table,
+                    included: copy_block.included,
+                    reason: copy_block.reason,
+                    rows: copy_block.rows,
+                    included_rows: copy_block.included_rows,
+                }
+            })
+            .collect()
+    }
+
+    /// Totals over all states of the included lines.
+    pub fn total_included(&self) -> Count {
+        total(&self.included)
+    }
+
+    /// Totals over all states of the dropped lines.
+    pub fn total_dropped(&self) -> Count {
+        total(&self.dropped)
+    }
+}
+
+fn total(counts: &BTreeMap<State, Count>) -> Count {
+    counts
+        .values()
+        .fold(Count::default(), |total, count| Count {
+            lines: total.lines + count.lines,
+            bytes: total.bytes + count.bytes,
+        })
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let row = |f: &mut fmt::Formatter<'_>, name: &str, included: Count, dropped: Count| {
+            writeln!(
+                f,
+                "{:<24}{:>16}{:>16}{:>16}{:>16}",
+                name, included.lines, included.bytes, dropped.lines, dropped.bytes
+            )
+        };
+        writeln!(
+            f,
+            "{:<24}{:>16}{:>16}{:>16}{:>16}",
+            "state", "included lines", "included bytes", "dropped lines", "dropped bytes"
+        )?;
+        let mut states: Vec<&State> = self.included.keys().chain(self.dropped.keys()).collect();
+        states.sort();
+        states.dedup();
+        for state in states {
+            let included = self.included.get(state).copied().unwrap_or_default();
+            let dropped = self.dropped.get(state).copied().unwrap_or_default();
+            row(f, &format!("{:?}", state), included, dropped)?;
+        }
+        row(f, "total", self.total_included(), self.total_dropped())
+    }
+}
@@ -0,0 +1,148 @@
+//! The table of contents comments pg_dump writes before every object of a plain dump, like
+//! `-- Name: foo; Type: TABLE; Schema: public; Owner: me` and
+//! `-- Data for Name: foo; Type: TABLE DATA; Schema: public; Owner: me`.
+
+const NAME: &[u8] = b"-- Name: ";
+pub(crate) const DATA_FOR_NAME: &[u8] = b"-- Data for Name: ";
+const TYPE: &[u8] = b"; Type: ";
+const SCHEMA: &str = "; Schema: ";
+
+/// The types of the objects in the data section of a dump, the others are part of the schema.
+const DATA_TYPES: [&[u8]; 5] = [
+    b"TABLE DATA",
+    b"SEQUENCE SET",
+    b"BLOBS",
+    b"BLOB DATA",
+    b"LARGE OBJECTS",
+];
+
+/// The types of the objects named after their table, like `foo foo_pkey` for a constraint.
+const TABLE_OBJECT_TYPES: [&str; 6] = [
+    "CONSTRAINT",
+    "FK CONSTRAINT",
+    "DEFAULT",
+    "TRIGGER",
+    "POLICY",
+    "RULE",
+];
+
+/// The table the object of a table of contents entry belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Owner {
+    /// The object is or belongs to this table, named like in a copy block header
+    Table(String),
+    /// An index, its table is in the `CREATE INDEX ... ON` statement after the entry
+    Index,
+    /// The owner of a sequence, its table is in the `ALTER SEQUENCE ... OWNED BY` statement after
+    /// the entry
+    SequenceOwnedBy,
+    /// An object of no table
+    None,
+}
+
+impl Owner {
+    /// The table the object of the entry belongs to, `None` if the line is no entry.
+    pub(crate) fn of_entry(buf: &[u8]) -> Option<Owner> {
+        let entry = crate::trim_newline(buf);
+        let rest = entry
+            .strip_prefix(NAME)
+            .or_else(|| entry.strip_prefix(DATA_FOR_NAME))?;
+        let type_start = crate::find(rest, TYPE)?;
+        let name = String::from_utf8_lossy(&rest[..type_start]);
+        let rest = String::from_utf8_lossy(&rest[type_start + TYPE.len()..]);
+        let (name, rest) = (&*name, &*rest);
+        let object_type = &rest[..rest.find(';').unwrap_or(rest.len())];
+        let schema = match rest.find(SCHEMA) {
+            Some(start) => {
+                let schema = &rest[start + SCHEMA.len()..];
+                &schema[..schema.find(';').unwrap_or(schema.len())]
+            }
+            None => "-",
+        };
+        let table = match object_type {
+            "INDEX" => return Some(Owner::Index),
+            "SEQUENCE OWNED BY" => return Some(Owner::SequenceOwnedBy),
+            "TABLE" | "TABLE DATA" | "ROW SECURITY" => Some(name),
+            "COMMENT" | "ACL" | "SECURITY LABEL" => match name.strip_prefix("TABLE ") {
+                Some(table) => Some(table),
+                None => name
+                    .strip_prefix("COLUMN ")
+                    .map(|column| column.split('.').next().unwrap_or(column)),
+            },
+            object_type if TABLE_OBJECT_TYPES.contains(&object_type) => name.split(' ').next(),
+            _ => None,
+        };
+        match table {
+            Some(table) if schema != "-" => {
+                Some(Owner::Table(format!("{}.{}", quote(schema), quote(table))))
+            }
+            _ => Some(Owner::None),
+        }
+    }
+}
+
+/// The table of a `CREATE INDEX ... ON table` statement as it is written in it.
+pub(crate) fn indexed_table(buf: &[u8]) -> Option<String> {
+    let statement = String::from_utf8_lossy(buf);
+    if !statement.starts_with("CREATE ") {
+        return None;
+    }
+    let rest = &statement[statement.find(" ON ")? + " ON ".len()..];
+    let rest = rest.strip_prefix("ONLY ").unwrap_or(rest);
+    let end = crate::header::position_unquoted(rest.as_bytes(), |b| {
+        b.is_ascii_whitespace() || b == b'(' || b == b';'
+    })
+    .unwrap_or(rest.len());
+    Some(rest[..end].to_string())
+}
+
+/// The table of an `ALTER SEQUENCE ... OWNED BY table.column` statement as it is written in it.
+pub(crate) fn sequence_owner(buf: &[u8]) -> Option<String> {
+    let statement = String::from_utf8_lossy(buf);
+    if !statement.starts_with("ALTER SEQUENCE ") {
+        return None;
+    }
+    let rest = &statement[statement.find(" OWNED BY ")? + " OWNED BY ".len()..];
+    let end =
+        crate::header::position_unquoted(rest.as_bytes(), |b| b.is_ascii_whitespace() || b == b';')
+            .unwrap_or(rest.len());
+    // the column is the last part of the name, `OWNED BY NONE` has no table
+    let mut quoted = false;
+    let column_start = rest.as_bytes()[..end].iter().rposition(|&b| {
+        if b == b'"' {
+            quoted = !quoted;
+        }
+        !quoted && b == b'.'
+    })?;
+    Some(rest[..column_start].to_string())
+}
+
+/// The name as a quoted identifier, the entries name their objects without quotes.
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Whether the lines up to the next entry of the table of contents are dump data or schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Section {
+    Data,
+    Schema,
+}
+
+impl Section {
+    /// The section of the object the comment is the table of contents entry of, `None` if the
+    /// line is no such comment.
+    pub(crate) fn of_entry(buf: &[u8]) -> Option<Section> {
+        if !buf.starts_with(NAME) && !buf.starts_with(DATA_FOR_NAME) {
+            return None;
+        }
+        let start = crate::find(buf, TYPE)? + TYPE.len();
+        let rest = crate::trim_newline(&buf[start..]);
+        let object_type = &rest[..rest.iter().position(|&b| b == b';').unwrap_or(rest.len())];
+        if DATA_TYPES.contains(&object_type) {
+            Some(Section::Data)
+        } else {
+            Some(Section::Schema)
+        }
+    }
+}
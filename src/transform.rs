@@ -0,0 +1,124 @@
+//! Piping the rows of a copy block through an external command.
+
+use std::io::{self, Read, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread::{self, JoinHandle};
+
+use crate::Result;
+
+/// A shell command the rows of a copy block are written to, its output takes their place.  The
+/// command is run by `sh -c`, or by `cmd /C` on Windows.
+pub(crate) struct Transform {
+    command: String,
+    child: Child,
+    stdin: Option<ChildStdin>,
+    /// The output of the command so far, read on a thread of its own so the command never waits
+    /// for its output to be read while its input is written
+    output: Receiver<Vec<u8>>,
+    reading: Option<JoinHandle<io::Result<()>>>,
+}
+
+impl Transform {
+    pub(crate) fn spawn(command: &str) -> Result<Transform> {
+        let mut shell = if cfg!(windows) {
+            let mut shell = Command::new("cmd");
+            shell.arg("/C");
+            shell
+        } else {
+            let mut shell = Command::new("sh");
+            shell.arg("-c");
+            shell
+        };
+        let mut child = shell
+            .arg(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("can not run the transform '{}': {}", command, e))?;
+        let stdin = child.stdin.take();
+        let mut stdout = child.stdout.take().expect("the output is piped");
+        let (sender, output) = mpsc::channel();
+        let reading = thread::spawn(move || {
+            let mut chunk = vec![0; 64 * 1024];
+            loop {
+                let read = match stdout.read(&mut chunk) {
+                    Ok(0) => return Ok(()),
+                    Ok(read) => read,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                };
+                if sender.send(chunk[..read].to_vec()).is_err() {
+                    return Ok(());
+                }
+            }
+        });
+        Ok(Transform {
+            command: command.to_string(),
+            child,
+            stdin,
+            output,
+            reading: Some(reading),
+        })
+    }
+
+    /// Writes the output of the command so far to `out`, without waiting for more.
+    pub(crate) fn write_output(&mut self, out: &mut dyn Write) -> Result<()> {
+        while let Ok(chunk) = self.output.try_recv() {
+            out.write_all(&chunk)?;
+        }
+        Ok(())
+    }
+
+    /// Ends the input of the command and writes the rest of its output to `out`, failing unless
+    /// the command succeeds.
+    pub(crate) fn finish(mut self, out: &mut dyn Write) -> Result<()> {
+        drop(self.stdin.take());
+        for chunk in &self.output {
+            out.write_all(&chunk)?;
+        }
+        if let Some(reading) = self.reading.take() {
+            reading
+                .join()
+                .map_err(|_| format!("reading the output of '{}' panicked", self.command))?
+                .map_err(|e| format!("reading the output of '{}': {}", self.command, e))?;
+        }
+        let status = self.child.wait()?;
+        if !status.success() {
+            return Err(format!("the transform '{}' failed with {}", self.command, status).into());
+        }
+        Ok(())
+    }
+}
+
+impl Write for Transform {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let stdin = self.stdin.as_mut().expect("the input is open until finish");
+        // a transform that stops reading its input fails, unlike the output closed by its reader,
+        // and an interrupted write is retried by `write_all`
+        stdin.write(buf).map_err(|e| match e.kind() {
+            io::ErrorKind::Interrupted => e,
+            _ => io::Error::other(format!(
+                "writing to the transform '{}': {}",
+                self.command, e
+            )),
+        })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.stdin {
+            Some(stdin) => stdin.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Transform {
+    // a transform left unfinished by an error is not waited for
+    fn drop(&mut self) {
+        if self.stdin.take().is_some() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// A PostgreSQL version like `14.2` or `9.6.24`, compared component by component so `14` is
+/// below `14.2` and `9.6` is below `10`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version(Vec<u32>);
+
+const DUMPED_FROM: &[u8] = b"-- Dumped from database version ";
+
+impl Version {
+    /// The server version in a `-- Dumped from database version 14.2 (Debian 14.2-1)` comment.
+    pub(crate) fn dumped_from(buf: &[u8]) -> Option<Version> {
+        let rest = std::str::from_utf8(buf.strip_prefix(DUMPED_FROM)?).ok()?;
+        // drop the distribution suffix and anything like `beta1` after the numbers
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        rest[..end].trim_end_matches('.').parse().ok()
+    }
+}
+
+impl FromStr for Version {
+    type Err = String;
+
+    fn from_str(version: &str) -> Result<Version, String> {
+        version
+            .split('.')
+            .map(|component| component.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map(Version)
+            .map_err(|_| format!("expected a version like 14 or 9.6, got '{}'", version))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let components: Vec<String> = self.0.iter().map(u32::to_string).collect();
+        write!(f, "{}", components.join("."))
+    }
+}
@@ -0,0 +1,98 @@
+use pgdump_filter::{filter, FilterConfig};
+
+/// A dump with a copy block in binary format of the integers, one per row.
+fn binary_dump(values: &[i32]) -> Vec<u8> {
+    let mut dump = b"SET x = 1;\nCOPY public.t (id) FROM stdin WITH (FORMAT binary);\n".to_vec();
+    dump.extend_from_slice(b"PGCOPY\n\xff\r\n\0");
+    dump.extend_from_slice(&0i32.to_be_bytes());
+    dump.extend_from_slice(&0i32.to_be_bytes());
+    for value in values {
+        dump.extend_from_slice(&1i16.to_be_bytes());
+        dump.extend_from_slice(&4i32.to_be_bytes());
+        dump.extend_from_slice(&value.to_be_bytes());
+    }
+    dump.extend_from_slice(&(-1i16).to_be_bytes());
+    dump.extend_from_slice(b"\\.\nSELECT 1;\n");
+    dump
+}
+
+fn filtered(dump: &[u8], config: &FilterConfig) -> pgdump_filter::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    filter(dump, &mut output, config)?;
+    Ok(output)
+}
+
+#[test]
+fn binary_copy_block_is_copied_verbatim() {
+    let dump = binary_dump(&[1, 2, 3]);
+    assert_eq!(filtered(&dump, &FilterConfig::default()).unwrap(), dump);
+}
+
+#[test]
+fn binary_rows_are_counted() {
+    let dump = binary_dump(&[1, 2, 3]);
+    let stats = filter(&dump[..], std::io::sink(), &FilterConfig::default()).unwrap();
+    assert_eq!(stats.copy_blocks[0].rows, 3);
+}
+
+#[test]
+fn max_rows_and_sample_fail_on_binary_copy_blocks() {
+    let dump = binary_dump(&[1, 2, 3]);
+    for config in [
+        FilterConfig {
+            max_rows: Some(0),
+            ..FilterConfig::default()
+        },
+        FilterConfig {
+            sample: Some(0.0),
+            ..FilterConfig::default()
+        },
+    ] {
+        let error = filtered(&dump, &config).unwrap_err().to_string();
+        assert!(error.contains("binary copy block of public.t"), "{}", error);
+    }
+}
+
+#[test]
+fn max_rows_leaves_excluded_binary_copy_blocks_alone() {
+    let dump = binary_dump(&[1, 2, 3]);
+    let config = FilterConfig {
+        max_rows: Some(1),
+        excluded_copy_blocks: vec!["t".to_string()],
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        filtered(&dump, &config).unwrap(),
+        b"SET x = 1;\nSELECT 1;\n"
+    );
+}
+
+#[test]
+fn truncated_binary_copy_block_keeps_only_header_and_trailer() {
+    let config = FilterConfig {
+        truncated_tables: vec!["public.t".to_string()],
+        ..FilterConfig::default()
+    };
+    let mut output = Vec::new();
+    let stats = filter(&binary_dump(&[1, 2, 3])[..], &mut output, &config).unwrap();
+    assert_eq!(output, binary_dump(&[]));
+    assert_eq!(stats.copy_blocks[0].rows, 3);
+    assert_eq!(stats.copy_blocks[0].included_rows, 0);
+}
+
+#[test]
+fn excluded_binary_copy_block_is_dropped_whole() {
+    // the bytes of the value read like a `\.` line and a comment
+    let tricky = i32::from_be_bytes(*b"\\.\n-");
+    let dump = binary_dump(&[tricky, 2]);
+    let config = FilterConfig {
+        excluded_copy_blocks: vec!["t".to_string()],
+        ..FilterConfig::default()
+    };
+    let stats = filter(&dump[..], std::io::sink(), &config).unwrap();
+    assert_eq!(
+        filtered(&dump, &config).unwrap(),
+        b"SET x = 1;\nSELECT 1;\n"
+    );
+    assert_eq!(stats.copy_blocks[0].rows, 2);
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use pgdump_filter::{filter, FilterConfig};
+
+/// A dump with the bytes a filter might be tempted to normalize: line endings, encodings,
+/// trailing whitespace, a `\r` inside a row and no newline at the end.
+const DUMP: &[u8] = b"--\r\n-- PostgreSQL database dump\r\n--\r\n\r\n\
+SET client_encoding = 'LATIN1';  \r\n\
+CREATE TABLE public.a (\r\n    v text\t\r\n);\r\n\r\n\r\n\
+COPY public.a (v) FROM stdin;\r\n\
+caf\xe9\r\n\
+  spaces  \n\
+cr\rinside\r\n\
+\\.\r\n\r\n\
+COPY public.b (v) FROM stdin;\n\
+\xff\xfe\n\
+\\.\n\
+SELECT 1;";
+
+fn lines(bytes: &[u8]) -> Vec<&[u8]> {
+    bytes.split_inclusive(|&b| b == b'\n').collect()
+}
+
+/// Whether every line of the output is a line of the dump, in the same order.
+fn is_made_of_lines_of(output: &[u8], dump: &[u8]) -> bool {
+    let mut dump_lines = lines(dump).into_iter();
+    lines(output)
+        .into_iter()
+        .all(|line| dump_lines.any(|dump_line| dump_line == line))
+}
+
+#[test]
+fn included_lines_are_written_byte_for_byte() {
+    let configs = [
+        FilterConfig::default(),
+        FilterConfig {
+            keep_comments: true,
+            keep_blank_lines: true,
+            ..FilterConfig::default()
+        },
+        FilterConfig {
+            excluded_copy_blocks: vec!["b".to_string()],
+            ..FilterConfig::default()
+        },
+        FilterConfig {
+            included_copy_blocks: vec!["b".to_string()],
+            strip_set: true,
+            ..FilterConfig::default()
+        },
+        FilterConfig {
+            data_only: true,
+            max_rows: Some(1),
+            ..FilterConfig::default()
+        },
+    ];
+    for (i, config) in configs.iter().enumerate() {
+        assert!(!config.rewrites_lines());
+        let mut output = Vec::new();
+        filter(DUMP, &mut output, config).unwrap();
+        assert!(
+            is_made_of_lines_of(&output, DUMP),
+            "config {}: {:?}",
+            i,
+            String::from_utf8_lossy(&output)
+        );
+    }
+}
+
+#[test]
+fn everything_is_kept_with_comments_and_blank_lines() {
+    let config = FilterConfig {
+        keep_comments: true,
+        keep_blank_lines: true,
+        ..FilterConfig::default()
+    };
+    let mut output = Vec::new();
+    filter(DUMP, &mut output, &config).unwrap();
+    assert_eq!(output, DUMP);
+}
+
+/// Latin-1 names in the headers, the `é` of `café` is a lone `\xe9`.
+const LATIN1_HEADERS: &[u8] = b"COPY public.\"caf\xe9\" (v) FROM stdin;\n\
+c1\n\
+\\.\n\
+COPY public.menu (\"prix_\xe9t\xe9\", v) FROM stdin;\n\
+m1\t1\n\
+\\.\n";
+
+fn filtered(dump: &[u8], config: &FilterConfig) -> Vec<u8> {
+    let mut output = Vec::new();
+    filter(dump, &mut output, config).unwrap();
+    output
+}
+
+#[test]
+fn headers_that_are_not_utf8_are_matched_on_their_bytes() {
+    assert_eq!(
+        filtered(LATIN1_HEADERS, &FilterConfig::default()),
+        LATIN1_HEADERS
+    );
+    let config = FilterConfig {
+        excluded_copy_blocks: vec!["menu".to_string()],
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        filtered(LATIN1_HEADERS, &config),
+        b"COPY public.\"caf\xe9\" (v) FROM stdin;\nc1\n\\.\n"
+    );
+    let config = FilterConfig {
+        excluded_copy_blocks: vec!["caf*".to_string()],
+        ..FilterConfig::default()
+    };
+    assert!(!filtered(LATIN1_HEADERS, &config).starts_with(b"COPY public.\"caf"));
+}
+
+#[test]
+fn a_leading_byte_order_mark_is_stripped_before_the_first_line() {
+    let dump = b"\xef\xbb\xbf-- a comment\nSET x = 1;\nCOPY public.a (v) FROM stdin;\n\xef\xbb\xbfa1\n\\.\n";
+    assert_eq!(
+        filtered(dump, &FilterConfig::default()),
+        b"SET x = 1;\nCOPY public.a (v) FROM stdin;\n\xef\xbb\xbfa1\n\\.\n"
+    );
+    let config = FilterConfig {
+        keep_bom: true,
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        filtered(dump, &config),
+        b"\xef\xbb\xbfSET x = 1;\nCOPY public.a (v) FROM stdin;\n\xef\xbb\xbfa1\n\\.\n"
+    );
+    let excluded = b"\xef\xbb\xbfCOPY public.a (v) FROM stdin;\na1\n\\.\nSELECT 1;\n";
+    let config = FilterConfig {
+        excluded_copy_blocks: vec!["a".to_string()],
+        ..FilterConfig::default()
+    };
+    assert_eq!(filtered(excluded, &config), b"SELECT 1;\n");
+}
@@ -0,0 +1,562 @@
+mod common;
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+use common::dump;
+
+/// Runs the command line tool on the dump given on stdin, expecting it to succeed.
+fn run(dump: impl AsRef<[u8]>, args: &[&str]) -> Output {
+    let output = run_to_end(dump, args);
+    assert!(output.status.success(), "{:?}", output);
+    output
+}
+
+/// Runs the command line tool on the dump given on stdin.
+fn run_to_end(dump: impl AsRef<[u8]>, args: &[&str]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pgdump_filter"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(dump.as_ref())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// An empty directory of its own for the test.
+fn temp_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pgdump_filter_{}_{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn quiet_writes_nothing_to_stderr() {
+    let dump = dump(&[("a", &["a1"])]);
+    let output = run(&dump, &["--quiet", "--timing", "--stats", "--count-rows"]);
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+}
+
+// the transform is a command of a Unix shell
+#[cfg(unix)]
+#[test]
+fn dry_run_runs_no_transforms() {
+    let marker = std::env::temp_dir().join(format!("pgdump_filter_dry_run_{}", std::process::id()));
+    let transform = format!("public.a=touch {}; cat", marker.display());
+    let output = run(
+        dump(&[("a", &["a1"])]),
+        &["--dry-run", "--transform", &transform],
+    );
+    let touched = marker.exists();
+    let _ = std::fs::remove_file(&marker);
+    assert!(!touched);
+    assert_eq!(output.stdout, b"");
+}
+
+#[test]
+fn start_and_end_byte_are_read_from_the_config_file() {
+    let dir = std::env::temp_dir().join(format!("pgdump_filter_config_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let dump = dump(&[("a", &["a1"]), ("b", &["b1"]), ("c", &["c1"])]);
+    let (start, end) = (dump.find("a1").unwrap(), dump.find("b1").unwrap());
+    let (input, config) = (dir.join("dump.sql"), dir.join("config.toml"));
+    std::fs::write(&input, &dump).unwrap();
+    let toml = format!("start_byte = {}\nend_byte = {}\n", start, end);
+    std::fs::write(&config, toml).unwrap();
+    let args = [
+        "--input",
+        input.to_str().unwrap(),
+        "--config",
+        config.to_str().unwrap(),
+    ];
+    let output = String::from_utf8(run("", &args).stdout).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(output, "COPY public.b (v) FROM stdin;\nb1\n\\.\n\n");
+}
+
+#[test]
+fn the_dump_is_read_from_the_input_file() {
+    let dir = temp_dir("input");
+    let input = dir.join("dump.sql");
+    let dump = dump(&[("a", &["a1"]), ("b", &["b1"])]);
+    fs::write(&input, &dump).unwrap();
+    let output = run("", &["--input", input.to_str().unwrap(), "-e", "b"]);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(output.stdout, run(&dump, &["-e", "b"]).stdout);
+}
+
+#[test]
+fn the_filtered_dump_is_written_to_the_output_file() {
+    let dir = temp_dir("output");
+    let output = dir.join("filtered.sql");
+    let dump = dump(&[("a", &["a1"]), ("b", &["b1"])]);
+    let stdout = run(&dump, &["-e", "b", "--output", output.to_str().unwrap()]).stdout;
+    let written = fs::read(&output).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(stdout, b"");
+    assert_eq!(written, run(&dump, &["-e", "b"]).stdout);
+}
+
+#[test]
+fn copy_blocks_are_read_from_the_list_files() {
+    let dir = temp_dir("lists");
+    let (excluded, included) = (dir.join("excluded.txt"), dir.join("included.txt"));
+    fs::write(&excluded, "# the big ones\na\n\n  c  \n").unwrap();
+    fs::write(&included, "b\n").unwrap();
+    let dump = dump(&[("a", &["a1"]), ("b", &["b1"]), ("c", &["c1"])]);
+    let args = ["--excluded_copy_blocks_file", excluded.to_str().unwrap()];
+    let without_excluded = run(&dump, &args).stdout;
+    let args = ["--included_copy_blocks_file", included.to_str().unwrap()];
+    let only_included = run(&dump, &args).stdout;
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(without_excluded, run(&dump, &["-e", "a", "-e", "c"]).stdout);
+    assert_eq!(only_included, run(&dump, &["-i", "b"]).stdout);
+    let output = String::from_utf8(only_included).unwrap();
+    assert!(output.contains("\nb1\n"), "{}", output);
+    assert!(
+        !output.contains("a1") && !output.contains("c1"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn a_gzip_compressed_dump_is_decompressed() {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    let dump = dump(&[("a", &["a1"]), ("b", &["b1"])]);
+    encoder.write_all(dump.as_bytes()).unwrap();
+    let output = run(encoder.finish().unwrap(), &["-e", "b"]);
+    assert_eq!(output.stdout, run(&dump, &["-e", "b"]).stdout);
+}
+
+#[test]
+fn an_output_file_ending_in_gz_is_gzip_compressed() {
+    let dir = temp_dir("gzip");
+    let path = dir.join("filtered.sql.gz");
+    let dump = dump(&[("a", &["a1"])]);
+    run(&dump, &["--output", path.to_str().unwrap()]);
+    let compressed = fs::read(&path).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    let mut written = String::new();
+    std::io::Read::read_to_string(
+        &mut flate2::read::GzDecoder::new(&compressed[..]),
+        &mut written,
+    )
+    .unwrap();
+    assert_eq!(written, String::from_utf8(run(&dump, &[]).stdout).unwrap());
+}
+
+#[test]
+fn stats_are_printed_to_stderr() {
+    let dump = "--\nSET x = 1;\nCOPY public.a (v) FROM stdin;\na1\n\\.\n";
+    let stderr = String::from_utf8(run(dump, &["--stats"]).stderr).unwrap();
+    let total = stderr.lines().find(|line| line.starts_with("total"));
+    let counts: Vec<&str> = total.unwrap().split_whitespace().skip(1).collect();
+    assert_eq!(counts, ["4", "47", "1", "3"], "{}", stderr);
+}
+
+#[test]
+fn list_prints_the_tables_of_the_copy_blocks() {
+    let dump = "SET x = 1;\n\
+                COPY public.c (v) FROM stdin;\nc1\n\\.\n\
+                COPY sales.\"B\" (v) FROM stdin;\nb1\n\\.\n\
+                COPY public.a (v) FROM stdin;\na1\n\\.\n";
+    assert_eq!(
+        String::from_utf8(run(dump, &["--list"]).stdout).unwrap(),
+        "public.c\nsales.\"B\"\npublic.a\n"
+    );
+}
+
+#[test]
+fn a_missing_included_copy_block_fails_the_run() {
+    let dump = dump(&[("a", &["a1"])]);
+    let output = run_to_end(
+        &dump,
+        &["-i", "a", "-i", "missing", "--require-all-included"],
+    );
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("not found in the dump: missing"),
+        "{}",
+        stderr
+    );
+    assert!(run_to_end(&dump, &["-i", "a", "-i", "missing"])
+        .status
+        .success());
+}
+
+#[test]
+fn progress_reports_the_bytes_processed_on_stderr() {
+    let output = run(dump(&[("a", &["a1"])]), &["--progress"]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains(" MB processed, "), "{}", stderr);
+    assert!(!output.stdout.is_empty());
+}
+
+#[test]
+fn the_copy_block_decisions_are_logged_at_debug_level() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pgdump_filter"))
+        .args(["-e", "a"])
+        .env("RUST_LOG", "debug")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let dump = dump(&[("a", &["a1"])]);
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(dump.as_bytes())
+        .unwrap();
+    let stderr = String::from_utf8(child.wait_with_output().unwrap().stderr).unwrap();
+    assert!(
+        stderr.contains("copy block public.a: ExcludedCopyBlock"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn options_on_the_command_line_take_precedence_over_the_config_file() {
+    let dir = temp_dir("precedence");
+    let config = dir.join("config.toml");
+    fs::write(
+        &config,
+        "excluded_copy_blocks = [\"a\"]\nkeep_comments = true\n",
+    )
+    .unwrap();
+    let dump = dump(&[("a", &["a1"]), ("b", &["b1"])]);
+    let from_file = run(&dump, &["--config", config.to_str().unwrap()]).stdout;
+    let args = ["--config", config.to_str().unwrap(), "-e", "b"];
+    let overridden = run(&dump, &args).stdout;
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        from_file,
+        run(&dump, &["-e", "a", "--keep-comments"]).stdout
+    );
+    assert_eq!(
+        overridden,
+        run(&dump, &["-e", "b", "--keep-comments"]).stdout
+    );
+    let (from_file, overridden) = (
+        String::from_utf8(from_file).unwrap(),
+        String::from_utf8(overridden).unwrap(),
+    );
+    assert!(
+        !from_file.contains("a1") && from_file.contains("b1"),
+        "{}",
+        from_file
+    );
+    assert!(from_file.contains("-- Name: a;"), "{}", from_file);
+    assert!(
+        overridden.contains("a1") && !overridden.contains("b1"),
+        "{}",
+        overridden
+    );
+}
+
+#[test]
+fn stats_json_writes_the_copy_blocks_as_json() {
+    let dir = temp_dir("stats_json");
+    let path = dir.join("stats.json");
+    let dump = dump(&[("a", &["a1", "a2"]), ("b", &["b1"])]);
+    run(&dump, &["-e", "b", "--stats-json", path.to_str().unwrap()]);
+    let stats: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    let blocks = stats["copy_blocks"].as_array().unwrap();
+    assert_eq!(blocks.len(), 2);
+    assert_eq!(blocks[0]["name"], "public.a");
+    assert_eq!(blocks[0]["included_rows"], 2);
+    assert_eq!(blocks[1]["included"], false);
+    assert_eq!(blocks[1]["reason"], "excluded");
+    assert_eq!(stats["dropped"]["ExcludedCopyBlock"]["lines"], 2);
+}
+
+#[test]
+fn input_files_are_read_one_after_the_other() {
+    let dir = temp_dir("inputs");
+    let (first, second) = (dir.join("1.sql"), dir.join("2.sql"));
+    // the copy block goes on in the second file
+    fs::write(&first, "SET x = 1;\nCOPY public.a (v) FROM stdin;\na1\n").unwrap();
+    fs::write(&second, "a2\n\\.\nSELECT 1;\n").unwrap();
+    let args = [
+        "-f",
+        first.to_str().unwrap(),
+        "-f",
+        second.to_str().unwrap(),
+        "-e",
+        "a",
+    ];
+    let output = run("", &args).stdout;
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "SET x = 1;\nSELECT 1;\n"
+    );
+}
+
+#[test]
+fn count_rows_prints_the_included_rows_per_table_by_name() {
+    let dump = dump(&[
+        ("c", &["c1", "c2", "c3"]),
+        ("a", &["a1"]),
+        ("b", &["b1", "b2"]),
+        ("d", &[]),
+    ]);
+    let output = run(dump, &["--count-rows", "-e", "b"]);
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "public.a: 1\npublic.c: 3\npublic.d: 0\n"
+    );
+}
+
+#[test]
+fn a_zstd_compressed_dump_is_decompressed() {
+    let dump = dump(&[("a", &["a1"])]);
+    let compressed = zstd::encode_all(dump.as_bytes(), 1).unwrap();
+    let output = run(compressed, &[]);
+    assert_eq!(output.stdout, run(&dump, &[]).stdout);
+}
+
+#[test]
+fn zstd_output_round_trips_by_suffix_and_by_compress() {
+    let dir = temp_dir("zstd");
+    let dump = dump(&[("a", &["a1"])]);
+    let expected = run(&dump, &[]).stdout;
+    let path = dir.join("filtered.sql.zst");
+    run(&dump, &["--output", path.to_str().unwrap()]);
+    let by_suffix = zstd::decode_all(&fs::read(&path).unwrap()[..]).unwrap();
+    let output = run(&dump, &["--compress=zstd"]);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(by_suffix, expected);
+    assert_eq!(zstd::decode_all(&output.stdout[..]).unwrap(), expected);
+    // and the filtered output is read back as the dump it came from
+    assert_eq!(run(output.stdout, &[]).stdout, expected);
+}
+
+#[test]
+fn manifest_writes_the_tables_as_json() {
+    let dir = temp_dir("manifest");
+    let path = dir.join("manifest.json");
+    let dump = dump(&[("a", &["a1"]), ("b", &["b1", "b2"])]);
+    run(dump, &["-e", "b", "--manifest", path.to_str().unwrap()]);
+    let manifest: serde_json::Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        manifest,
+        serde_json::json!([
+            {"schema": "public", "table": "a", "included": true, "reason": "included",
+             "rows": 1, "included_rows": 1},
+            {"schema": "public", "table": "b", "included": false, "reason": "excluded",
+             "rows": 2, "included_rows": 0},
+        ])
+    );
+}
+
+#[test]
+fn errors_are_printed_with_their_context() {
+    let missing = temp_dir("errors").join("missing.sql");
+    let output = run_to_end("", &["--input", missing.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("error: "), "{}", stderr);
+    assert!(stderr.contains("missing.sql"), "{}", stderr);
+    let output = run_to_end(
+        "SELECT 1;\nthis line is too long\n",
+        &["--max-line-bytes", "10"],
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("error: line 2: "), "{}", stderr);
+    assert!(!stderr.contains('\x1b'), "{}", stderr);
+}
+
+#[test]
+fn a_failed_run_leaves_no_output_file_behind() {
+    let dir = temp_dir("atomic");
+    let path = dir.join("filtered.sql");
+    let mut dump = dump(&[("a", &["a1"])]);
+    dump.push_str(&"x".repeat(200));
+    let output = run_to_end(
+        &dump,
+        &[
+            "--max-line-bytes",
+            "100",
+            "--output",
+            path.to_str().unwrap(),
+        ],
+    );
+    assert!(!output.status.success());
+    let left: Vec<_> = fs::read_dir(&dir).unwrap().collect();
+    assert!(left.is_empty(), "{:?}", left);
+    // an existing output file is only replaced by a complete one
+    fs::write(&path, "before").unwrap();
+    run_to_end(
+        &dump,
+        &[
+            "--max-line-bytes",
+            "100",
+            "--output",
+            path.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap(), "before");
+    run(&dump, &["--output", path.to_str().unwrap()]);
+    let written = fs::read_to_string(&path).unwrap();
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(written.ends_with(&"x".repeat(200)), "{}", written);
+}
+
+#[test]
+fn include_from_query_output_reads_the_tables_of_a_psql_listing() {
+    let dir = temp_dir("query_output");
+    let listing = dir.join("tables.txt");
+    fs::write(
+        &listing,
+        " table_name | rows\n------------+------\n a          |   12\n c          |    3\n(2 rows)\n\n",
+    )
+    .unwrap();
+    let plain = dir.join("plain.txt");
+    fs::write(&plain, "b\n\n").unwrap();
+    let dump = dump(&[("a", &["a1"]), ("b", &["b1"]), ("c", &["c1"])]);
+    let stdout = |listing: &std::path::Path| {
+        let args = ["--include-from-query-output", listing.to_str().unwrap()];
+        String::from_utf8(run(&dump, &args).stdout).unwrap()
+    };
+    let from_listing = stdout(&listing);
+    let from_plain = stdout(&plain);
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(
+        from_listing.contains("\na1\n") && from_listing.contains("\nc1\n"),
+        "{}",
+        from_listing
+    );
+    assert!(!from_listing.contains("b1"), "{}", from_listing);
+    assert!(from_plain.contains("\nb1\n"), "{}", from_plain);
+    assert!(!from_plain.contains("a1") && !from_plain.contains("c1"));
+}
+
+#[test]
+fn a_closed_output_ends_the_run_without_an_error() {
+    let rows: Vec<String> = (0..200_000).map(|i| format!("row {}", i)).collect();
+    let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+    let dump = dump(&[("a", &rows)]);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pgdump_filter"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = std::thread::spawn(move || {
+        // the tool may stop reading once its output is gone
+        let _ = stdin.write_all(dump.as_bytes());
+    });
+    // like `head -c 100`
+    let mut head = [0; 100];
+    std::io::Read::read_exact(child.stdout.as_mut().unwrap(), &mut head).unwrap();
+    drop(child.stdout.take());
+    let output = child.wait_with_output().unwrap();
+    writer.join().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+}
+
+#[test]
+fn summary_only_prints_the_tables_instead_of_the_dump() {
+    let dump = "SELECT 1;\n\
+                COPY public.users (id, name, email) FROM stdin;\n1\ta\tb\n2\tc\td\n\\.\n\
+                COPY public.events FROM stdin;\n1\n\\.\n";
+    let output = run(dump, &["--summary-only"]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "table                    rows  columns\n\
+         public.users                2  id, name, email\n\
+         public.events               1\n"
+    );
+}
+
+#[test]
+fn the_output_is_the_same_whatever_the_buffer_sizes() {
+    let long_row = "x".repeat(5000);
+    let mut rows: Vec<String> = (0..2000).map(|i| format!("row {}", i)).collect();
+    rows.push(long_row);
+    let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+    let dump = dump(&[("a", &rows), ("b", &["b1"])]);
+    let expected = run(&dump, &["-e", "b"]).stdout;
+    for args in [
+        ["--read-buffer-kb", "1"],
+        ["--write-buffer-kb", "1"],
+        ["--read-buffer-kb", "1024"],
+        ["--write-buffer-kb", "1024"],
+    ] {
+        let output = run(&dump, &["-e", "b", args[0], args[1]]);
+        assert!(output.stdout == expected, "{:?}", args);
+    }
+    let output = run(
+        &dump,
+        &[
+            "-e",
+            "b",
+            "--read-buffer-kb",
+            "1",
+            "--write-buffer-kb",
+            "1",
+            "--buffersize",
+            "1",
+        ],
+    );
+    assert!(output.stdout == expected);
+}
+
+#[test]
+fn quiet_silences_the_progress_warnings_and_reports_to_stderr() {
+    let dump = dump(&[("a", &["a1"])]);
+    let loud = [
+        "--progress",
+        "--stats-json",
+        "-",
+        "--manifest",
+        "-",
+        "--strict-lo",
+        "-s",
+        "missing",
+        "-e",
+        "a",
+    ];
+    let output = run(&dump, &loud);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("MB processed"), "{}", stderr);
+    assert!(stderr.contains("warning: "), "{}", stderr);
+    let quiet: Vec<&str> = loud.iter().copied().chain(Some("--quiet")).collect();
+    assert_eq!(String::from_utf8(run(&dump, &quiet).stderr).unwrap(), "");
+    let quiet_dry_run: Vec<&str> = quiet.iter().copied().chain(Some("--dry-run")).collect();
+    assert_eq!(
+        String::from_utf8(run(&dump, &quiet_dry_run).stderr).unwrap(),
+        ""
+    );
+}
+
+#[test]
+fn excluded_and_included_copy_blocks_can_not_be_combined() {
+    let dump = dump(&[("a", &["a1"]), ("b", &["b1"])]);
+    let output = run_to_end(&dump, &["-e", "a", "-i", "b"]);
+    assert!(!output.status.success());
+    assert_eq!(output.stdout, b"");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("cannot be used with"), "{}", stderr);
+}
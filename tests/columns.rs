@@ -0,0 +1,107 @@
+mod common;
+
+use common::{dump, output, output_in_parts};
+use pgdump_filter::FilterConfig;
+
+fn masked(key: Option<&str>) -> FilterConfig {
+    FilterConfig {
+        mask_columns: vec!["public.a.v".to_string(), "public.b.v".to_string()],
+        mask_hash: true,
+        mask_key: key.map(str::to_string),
+        ..FilterConfig::default()
+    }
+}
+
+/// The values of the rows of the output, in order.
+fn rows(output: &str) -> Vec<&str> {
+    let mut rows = vec![];
+    let mut in_copy_block = false;
+    for line in output.lines() {
+        match line {
+            "\\." => in_copy_block = false,
+            _ if in_copy_block => rows.push(line),
+            _ => in_copy_block = line.starts_with("COPY "),
+        }
+    }
+    rows
+}
+
+#[test]
+fn masked_values_are_hashed_alike_with_the_same_key() {
+    let dump = dump(&[("a", &["x", "y", "\\N"]), ("b", &["x"])]);
+    let first = output(&dump, &masked(Some("secret")));
+    let hashes = rows(&first);
+    assert_eq!(hashes[0], hashes[3]);
+    assert_ne!(hashes[0], hashes[1]);
+    assert_eq!(hashes[2], "\\N");
+    assert!(!first.contains("\nx\n"));
+    assert_eq!(output(&dump, &masked(Some("secret"))), first);
+    assert_ne!(output(&dump, &masked(Some("other"))), first);
+}
+
+#[test]
+fn masked_values_are_hashed_with_a_random_key_without_one() {
+    let dump = dump(&[("a", &["x"]), ("b", &["x"])]);
+    let first = output(&dump, &masked(None));
+    assert_ne!(output(&dump, &masked(None)), first);
+    let hashes = rows(&first);
+    assert_eq!(hashes[0], hashes[1]);
+}
+
+#[test]
+fn the_parts_of_a_dump_hash_with_the_same_random_key() {
+    // the middle of the dump is in the rows of `a`, the second part starts at the copy block of `b`
+    let filler = "f".repeat(1000);
+    let dump = dump(&[("a", &["x", &filler]), ("b", &["x"])]);
+    let output = output_in_parts(&dump, &masked(None), 2);
+    let hashes = rows(&output);
+    assert_eq!(hashes.len(), 3);
+    assert_eq!(hashes[0], hashes[2]);
+}
+
+const THREE_COLUMNS: &str = "COPY public.people (id, email, name) FROM stdin;\n\
+1\tme@example.com\tMe\n\
+2\t\\N\tyou\\tthem\n\
+3\ta\\\\tb\t\\N\n\
+\\.\n\
+COPY public.other (id, email) FROM stdin;\n\
+1\tkept@example.com\n\
+\\.\n";
+
+#[test]
+fn dropped_columns_leave_the_header_and_the_rows() {
+    let config = FilterConfig {
+        drop_columns: vec!["public.people.email".to_string()],
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        output(THREE_COLUMNS, &config),
+        "COPY public.people (id, name) FROM stdin;\n\
+         1\tMe\n\
+         2\tyou\\tthem\n\
+         3\t\\N\n\
+         \\.\n\
+         COPY public.other (id, email) FROM stdin;\n\
+         1\tkept@example.com\n\
+         \\.\n"
+    );
+}
+
+#[test]
+fn dropping_every_listed_column_keeps_the_others_in_order() {
+    let config = FilterConfig {
+        drop_columns: vec![
+            "public.people.id".to_string(),
+            "public.people.name".to_string(),
+        ],
+        ..FilterConfig::default()
+    };
+    let output = output(THREE_COLUMNS, &config);
+    assert!(
+        output.starts_with(
+            "COPY public.people (email) FROM stdin;\nme@example.com\n\\N\na\\\\tb\n\\.\n"
+        ),
+        "{}",
+        output
+    );
+}
@@ -0,0 +1,70 @@
+//! Helpers shared by the integration tests: dumps built in memory and filtered in memory.
+
+#![allow(dead_code)]
+
+use std::io::Cursor;
+
+use pgdump_filter::{copy_block_boundary, filter, filter_parts, FilterConfig, Stats};
+
+/// A dump laid out like pg_dump writes it, with a table and a copy block with the rows for every
+/// `(table, rows)` entry, in the `public` schema.
+pub fn dump(tables: &[(&str, &[&str])]) -> String {
+    let mut dump = String::from(
+        "--\n-- PostgreSQL database dump\n--\n\nSET statement_timeout = 0;\n\
+         SET client_encoding = 'UTF8';\n\n",
+    );
+    for (table, _) in tables {
+        dump.push_str(&format!(
+            "--\n-- Name: {0}; Type: TABLE; Schema: public; Owner: me\n--\n\n\
+             CREATE TABLE public.{0} (\n    v text\n);\n\n\n\
+             ALTER TABLE public.{0} OWNER TO me;\n\n",
+            table
+        ));
+    }
+    for (table, rows) in tables {
+        dump.push_str(&format!(
+            "--\n-- Data for Name: {0}; Type: TABLE DATA; Schema: public; Owner: me\n--\n\n\
+             COPY public.{0} (v) FROM stdin;\n",
+            table
+        ));
+        for row in *rows {
+            dump.push_str(row);
+            dump.push('\n');
+        }
+        dump.push_str("\\.\n\n\n");
+    }
+    dump.push_str("--\n-- PostgreSQL database dump complete\n--\n\n");
+    dump
+}
+
+/// The output of filtering the dump.
+pub fn output(dump: &str, config: &FilterConfig) -> String {
+    let (output, _) = filtered(dump, config);
+    output
+}
+
+/// The output and the statistics of filtering the dump.
+pub fn filtered(dump: &str, config: &FilterConfig) -> (String, Stats) {
+    let mut output = Vec::new();
+    let stats = filter(dump.as_bytes(), &mut output, config).unwrap();
+    (String::from_utf8(output).unwrap(), stats)
+}
+
+/// The output of filtering the dump in `jobs` parts between copy block boundaries.
+pub fn output_in_parts(dump: &str, config: &FilterConfig, jobs: u64) -> String {
+    let bytes = dump.as_bytes();
+    let size = bytes.len() as u64;
+    let mut cursor = Cursor::new(bytes);
+    let mut offsets = vec![];
+    for i in 0..jobs {
+        offsets.push(copy_block_boundary(&mut cursor, size * i / jobs).unwrap() as usize);
+    }
+    offsets.push(bytes.len());
+    let parts: Vec<&[u8]> = offsets
+        .windows(2)
+        .map(|window| &bytes[window[0]..window[1]])
+        .collect();
+    let mut output = Vec::new();
+    filter_parts(parts, &mut output, config).unwrap();
+    String::from_utf8(output).unwrap()
+}
@@ -0,0 +1,304 @@
+mod common;
+
+use common::{dump, filtered, output};
+use pgdump_filter::FilterConfig;
+
+/// Rows of a table that read like comments, empty lines and copy block headers.
+const TRICKY_ROWS: &[&str] = &[
+    "-- not a comment",
+    "--",
+    "",
+    "",
+    "COPY public.b (v) FROM stdin;",
+    "COPY secret",
+    "\\\\.",
+];
+
+#[test]
+fn rows_of_an_included_copy_block_are_copied_verbatim() {
+    let dump = dump(&[("a", TRICKY_ROWS), ("b", &["b1"])]);
+    let output = output(&dump, &FilterConfig::default());
+    let rows = TRICKY_ROWS.join("\n");
+    assert!(
+        output.contains(&format!("COPY public.a (v) FROM stdin;\n{}\n\\.\n", rows)),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn rows_of_an_excluded_copy_block_are_dropped_whole() {
+    let dump = dump(&[("a", TRICKY_ROWS), ("b", &["b1"])]);
+    let config = FilterConfig {
+        excluded_copy_blocks: vec!["a".to_string()],
+        ..FilterConfig::default()
+    };
+    let output = output(&dump, &config);
+    assert!(!output.contains("public.a (v)"), "{}", output);
+    assert!(!output.contains("not a comment"), "{}", output);
+    assert!(!output.contains("COPY secret"), "{}", output);
+    assert!(
+        output.contains("COPY public.b (v) FROM stdin;\nb1\n\\.\n"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn comments_and_empty_lines_outside_copy_blocks_are_dropped() {
+    let dump = dump(&[("a", &["a1"])]);
+    let output = output(&dump, &FilterConfig::default());
+    assert!(!output.contains("-- Name:"), "{}", output);
+    assert!(!output.contains("\n\n\n"), "{}", output);
+}
+
+#[test]
+fn strict_fails_on_a_copy_block_without_terminator() {
+    let dump = "COPY public.a (v) FROM stdin;\na1\n";
+    let config = FilterConfig {
+        strict: true,
+        ..FilterConfig::default()
+    };
+    let error = pgdump_filter::filter(dump.as_bytes(), std::io::sink(), &config).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("inside the copy block of public.a"),
+        "{}",
+        error
+    );
+    assert_eq!(output(dump, &FilterConfig::default()), dump);
+}
+
+#[test]
+fn headers_give_the_table_and_its_columns() {
+    let dump = "COPY public.\"My Table\" (\"Id\", v) FROM stdin;\n\\.\n\
+                COPY public.b FROM stdin;\n\\.\n\
+                COPY \"Sales\".c (v) FROM stdin WITH (FORMAT text);\n\\.\n";
+    let (_, stats) = filtered(dump, &FilterConfig::default());
+    let blocks: Vec<(&str, Vec<&str>)> = stats
+        .copy_blocks
+        .iter()
+        .map(|block| {
+            let columns = block.columns.iter().map(String::as_str).collect();
+            (block.name.as_str(), columns)
+        })
+        .collect();
+    assert_eq!(
+        blocks,
+        vec![
+            ("public.\"My Table\"", vec!["\"Id\"", "v"]),
+            ("public.b", vec![]),
+            ("\"Sales\".c", vec!["v"]),
+        ]
+    );
+}
+
+#[test]
+fn long_rows_are_streamed_whole() {
+    let long_row = "x".repeat(10_000);
+    let dump = dump(&[("a", &["a1", &long_row, "a3"]), ("b", &[&long_row])]);
+    let config = FilterConfig {
+        stream_rows_over: Some(64),
+        excluded_copy_blocks: vec!["b".to_string()],
+        ..FilterConfig::default()
+    };
+    let (streamed, stats) = filtered(&dump, &config);
+    let whole = output(
+        &dump,
+        &FilterConfig {
+            excluded_copy_blocks: vec!["b".to_string()],
+            ..FilterConfig::default()
+        },
+    );
+    assert_eq!(streamed, whole);
+    assert_eq!(stats.copy_blocks[0].rows, 3);
+    assert_eq!(stats.copy_blocks[1].bytes, 10_001);
+}
+
+#[test]
+fn max_line_bytes_fails_on_a_longer_line() {
+    let dump = dump(&[("a", &[&"x".repeat(100)])]);
+    let config = FilterConfig {
+        max_line_bytes: Some(50),
+        ..FilterConfig::default()
+    };
+    assert!(pgdump_filter::filter(dump.as_bytes(), std::io::sink(), &config).is_err());
+}
+
+#[test]
+fn rows_starting_with_the_terminator_do_not_end_the_copy_block() {
+    let dump = dump(&[("a", &["\\.something", "\\.\t1", "a1"]), ("b", &["b1"])]);
+    let config = FilterConfig {
+        excluded_copy_blocks: vec!["a".to_string()],
+        ..FilterConfig::default()
+    };
+    let output = output(&dump, &config);
+    assert!(!output.contains("something"), "{}", output);
+    assert!(!output.contains("a1"), "{}", output);
+    assert!(output.contains("COPY public.b (v) FROM stdin;\nb1\n\\.\n"));
+    let (output, stats) = filtered(&dump, &FilterConfig::default());
+    assert!(
+        output.contains("COPY public.a (v) FROM stdin;\n\\.something\n\\.\t1\na1\n\\.\n"),
+        "{}",
+        output
+    );
+    assert_eq!(stats.copy_blocks.len(), 2);
+    assert_eq!(stats.copy_blocks[0].rows, 3);
+}
+
+#[test]
+fn the_terminator_ends_the_copy_block_at_the_end_of_the_dump_or_before_a_crlf() {
+    let config = FilterConfig {
+        excluded_copy_blocks: vec!["a".to_string()],
+        ..FilterConfig::default()
+    };
+    let at_end = "COPY public.a (v) FROM stdin;\na1\n\\.";
+    assert_eq!(output(at_end, &config), "");
+    let crlf = "COPY public.a (v) FROM stdin;\r\na1\r\n\\.\r\nSELECT 1;\r\n";
+    assert_eq!(output(crlf, &config), "SELECT 1;\r\n");
+}
+
+const WRAPPED_HEADER: &str = "SET x = 1;\n\
+COPY public.a (\n    id,\n    v) FROM stdin;\n\
+1\ta1\n\
+\\.\n\
+COPY public.b (id, v)\nFROM stdin;\n\
+1\tb1\n\
+\\.\n\
+SELECT 1;\n";
+
+#[test]
+fn wrapped_headers_start_a_copy_block() {
+    let config = FilterConfig {
+        excluded_copy_blocks: vec!["a".to_string()],
+        ..FilterConfig::default()
+    };
+    let (output, stats) = filtered(WRAPPED_HEADER, &config);
+    assert_eq!(
+        output,
+        "SET x = 1;\nCOPY public.b (id, v)\nFROM stdin;\n1\tb1\n\\.\nSELECT 1;\n"
+    );
+    let blocks: Vec<_> = stats
+        .copy_blocks
+        .iter()
+        .map(|block| (block.name.as_str(), block.columns.clone(), block.rows))
+        .collect();
+    let columns = vec!["id".to_string(), "v".to_string()];
+    assert_eq!(
+        blocks,
+        vec![("public.a", columns.clone(), 1), ("public.b", columns, 1)]
+    );
+    assert_eq!(
+        filtered(WRAPPED_HEADER, &FilterConfig::default()).0,
+        WRAPPED_HEADER
+    );
+}
+
+/// The error of filtering the rows of a copy block with two columns, with `validate_rows`.
+fn validation_error(rows: &str) -> Option<String> {
+    let dump = format!(
+        "SELECT 1;\nCOPY public.a (id, v) FROM stdin;\n{}\\.\n",
+        rows
+    );
+    let config = FilterConfig {
+        validate_rows: true,
+        ..FilterConfig::default()
+    };
+    pgdump_filter::filter(dump.as_bytes(), std::io::sink(), &config)
+        .err()
+        .map(|error| error.to_string())
+}
+
+#[test]
+fn validate_rows_accepts_the_rows_pg_dump_writes() {
+    assert_eq!(
+        validation_error("1\t\\N\n2\ta\\\\b\\tc\\n\\r\\b\\f\\v\\011\\x4a\n3\t\n"),
+        None
+    );
+}
+
+#[test]
+fn validate_rows_fails_on_the_first_row_with_another_number_of_fields() {
+    let error = validation_error("1\ta\n2\n3\ta\tb\n").unwrap();
+    assert!(error.starts_with("line 4: "), "{}", error);
+    assert!(error.contains("1 fields, expected 2"), "{}", error);
+    let error = validation_error("1\ta\t\n").unwrap();
+    assert!(error.contains("3 fields, expected 2"), "{}", error);
+}
+
+#[test]
+fn validate_rows_fails_on_a_malformed_escape() {
+    let error = validation_error("1\ta\n2\tb\\q\n").unwrap();
+    assert!(error.starts_with("line 4: "), "{}", error);
+    assert!(error.contains("\\q"), "{}", error);
+    assert!(validation_error("1\ta\\N\n").is_some());
+    assert!(validation_error("1\ta\\\n").is_some());
+    assert!(validation_error("1\t\\xg\n").is_some());
+}
+
+#[test]
+fn validate_rows_checks_only_the_included_copy_blocks() {
+    let dump = "COPY public.a (id, v) FROM stdin;\nshort\n\\.\n";
+    let config = FilterConfig {
+        validate_rows: true,
+        excluded_copy_blocks: vec!["a".to_string()],
+        ..FilterConfig::default()
+    };
+    assert!(pgdump_filter::filter(dump.as_bytes(), std::io::sink(), &config).is_ok());
+}
+
+const TO_STDOUT: &str = "SELECT 1;\n\
+COPY public.a (v) TO stdout;\nCOPY secret\n\\.\n\
+COPY public.b TO stdout WITH (FORMAT text);\nb1\n\\.\n\
+SELECT 2;\n";
+
+#[test]
+fn to_stdout_blocks_are_copy_blocks() {
+    assert_eq!(output(TO_STDOUT, &FilterConfig::default()), TO_STDOUT);
+    let config = FilterConfig {
+        excluded_copy_blocks: vec!["a".to_string()],
+        ..FilterConfig::default()
+    };
+    let (output, stats) = filtered(TO_STDOUT, &config);
+    assert_eq!(
+        output,
+        "SELECT 1;\nCOPY public.b TO stdout WITH (FORMAT text);\nb1\n\\.\nSELECT 2;\n"
+    );
+    let names: Vec<&str> = stats.copy_blocks.iter().map(|b| b.name.as_str()).collect();
+    assert_eq!(names, ["public.a", "public.b"]);
+}
+
+/// The result of filtering a dump whose only row is `len` bytes long with its newline.
+fn with_row_of(len: usize, max_line_bytes: usize) -> pgdump_filter::Result<String> {
+    let dump = format!(
+        "COPY public.a (v) FROM stdin;\n{}\n\\.\n",
+        "x".repeat(len - 1)
+    );
+    let config = FilterConfig {
+        max_line_bytes: Some(max_line_bytes),
+        ..FilterConfig::default()
+    };
+    let mut output = Vec::new();
+    pgdump_filter::filter(dump.as_bytes(), &mut output, &config)?;
+    Ok(String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn max_line_bytes_counts_the_newline() {
+    assert!(with_row_of(100, 100).unwrap().contains(&"x".repeat(99)));
+    assert!(with_row_of(99, 100).is_ok());
+    let error = with_row_of(101, 100).unwrap_err();
+    assert!(error.to_string().starts_with("line 2: "), "{}", error);
+}
+
+#[test]
+fn lines_grow_past_the_line_buffer_without_max_line_bytes() {
+    let row = "x".repeat(3 * 1024 * 1024);
+    let dump = dump(&[("a", &[&row])]);
+    let config = FilterConfig {
+        buffersize_in_mb: 1,
+        ..FilterConfig::default()
+    };
+    assert!(output(&dump, &config).contains(&format!("\n{}\n", row)));
+}
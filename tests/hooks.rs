@@ -0,0 +1,81 @@
+mod common;
+
+use common::{dump, output};
+use pgdump_filter::FilterConfig;
+
+fn hooks() -> FilterConfig {
+    FilterConfig {
+        before_block: Some("TRUNCATE %s;".to_string()),
+        after_block: Some("ANALYZE %s;".to_string()),
+        excluded_copy_blocks: vec!["c".to_string()],
+        ..FilterConfig::default()
+    }
+}
+
+#[test]
+fn hooks_go_right_around_every_included_copy_block() {
+    let dump = dump(&[("a", &["a1"]), ("b", &["b1", "b2"]), ("c", &["c1"])]);
+    let output = output(&dump, &hooks());
+    assert!(
+        output.contains(
+            "TRUNCATE public.a;\nCOPY public.a (v) FROM stdin;\na1\n\\.\nANALYZE public.a;\n"
+        ),
+        "{}",
+        output
+    );
+    assert!(
+        output.contains(
+            "TRUNCATE public.b;\nCOPY public.b (v) FROM stdin;\nb1\nb2\n\\.\nANALYZE public.b;\n"
+        ),
+        "{}",
+        output
+    );
+    assert!(!output.contains("public.c;"), "{}", output);
+    assert_eq!(output.matches("TRUNCATE ").count(), 2);
+    assert_eq!(output.matches("ANALYZE ").count(), 2);
+    // nothing else moved
+    let without_hooks = output
+        .replace("TRUNCATE public.a;\n", "")
+        .replace("TRUNCATE public.b;\n", "")
+        .replace("ANALYZE public.a;\n", "")
+        .replace("ANALYZE public.b;\n", "");
+    let config = FilterConfig {
+        excluded_copy_blocks: vec!["c".to_string()],
+        ..FilterConfig::default()
+    };
+    assert_eq!(without_hooks, common::output(&dump, &config));
+}
+
+#[test]
+fn hooks_go_right_around_the_inserts_of_a_copy_block() {
+    let dump = dump(&[("a", &["a1"]), ("b", &["b1", "b2"]), ("c", &["c1"])]);
+    let config = FilterConfig {
+        to_inserts: true,
+        ..hooks()
+    };
+    let output = output(&dump, &config);
+    assert!(
+        output.contains(
+            "TRUNCATE public.a;\n\
+             INSERT INTO public.a (v) VALUES ('a1');\n\
+             ANALYZE public.a;\n"
+        ),
+        "{}",
+        output
+    );
+    assert!(
+        output.contains(
+            "TRUNCATE public.b;\n\
+             INSERT INTO public.b (v) VALUES ('b1');\n\
+             INSERT INTO public.b (v) VALUES ('b2');\n\
+             ANALYZE public.b;\n"
+        ),
+        "{}",
+        output
+    );
+    assert!(
+        !output.contains("public.c;") && !output.contains("COPY "),
+        "{}",
+        output
+    );
+}
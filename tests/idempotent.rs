@@ -0,0 +1,126 @@
+mod common;
+
+use common::output;
+use pgdump_filter::FilterConfig;
+
+/// Rows that read like other lines of a dump.
+const ROWS: &[&str] = &[
+    "1\tplain",
+    "2\t-- dashes",
+    "3\t",
+    "4\t\\N",
+    "5\tCOPY x",
+    "6\t\\\\.",
+];
+
+/// A dump of random objects, inside and outside of copy blocks.
+fn random_dump(rng: &mut fastrand::Rng) -> String {
+    let mut dump = String::from(
+        "--\n-- PostgreSQL database dump\n--\n\n\
+         SET statement_timeout = 0;\nSELECT pg_catalog.set_config('search_path', '', false);\n\n",
+    );
+    for i in 0..rng.usize(1..8) {
+        let table = ["a", "b", "c"][rng.usize(..3)];
+        match rng.usize(..7) {
+            0 => dump.push_str(&format!(
+                "--\n-- Name: {0}; Type: TABLE; Schema: public; Owner: me\n--\n\n\
+                 CREATE TABLE public.{0} (\n    id integer,\n    v text\n);\n\n\n",
+                table
+            )),
+            1 => dump.push_str(&format!(
+                "CREATE FUNCTION public.f{}() RETURNS text\n    AS $$\n-- body\n\nSELECT 1;\n$$;\n\n",
+                i
+            )),
+            2 => dump.push_str(&format!(
+                "ALTER TABLE public.{} OWNER TO me;\nGRANT ALL ON TABLE public.{0} TO me;\n",
+                table
+            )),
+            3 => dump.push_str(&format!(
+                "SELECT pg_catalog.setval('public.{}_id_seq', {}, true);\n\n\n\n",
+                table, i
+            )),
+            _ => {
+                dump.push_str(&format!(
+                    "--\n-- Data for Name: {0}; Type: TABLE DATA; Schema: public; Owner: me\n--\n\n\
+                     COPY public.{0} (id, v) FROM stdin;\n",
+                    table
+                ));
+                for _ in 0..rng.usize(..6) {
+                    dump.push_str(ROWS[rng.usize(..ROWS.len())]);
+                    dump.push('\n');
+                }
+                dump.push_str("\\.\n\n\n");
+            }
+        }
+    }
+    dump.push_str("--\n-- PostgreSQL database dump complete\n--\n\n");
+    dump
+}
+
+/// Configs of the options that filter the dump rather than change its data.
+fn configs() -> Vec<FilterConfig> {
+    let base = FilterConfig::default;
+    vec![
+        base(),
+        FilterConfig {
+            excluded_copy_blocks: vec!["a".to_string()],
+            ..base()
+        },
+        FilterConfig {
+            included_copy_blocks: vec!["b".to_string(), "c".to_string()],
+            ..base()
+        },
+        FilterConfig {
+            keep_comments: true,
+            keep_blank_lines: true,
+            ..base()
+        },
+        FilterConfig {
+            strip_set: true,
+            strip_search_path: true,
+            strip_grants: true,
+            strip_owner: true,
+            ..base()
+        },
+        FilterConfig {
+            schema_only: true,
+            keep_setval: true,
+            ..base()
+        },
+        FilterConfig {
+            data_only: true,
+            ..base()
+        },
+        FilterConfig {
+            max_rows: Some(2),
+            ..base()
+        },
+        FilterConfig {
+            drop_columns: vec!["public.a.v".to_string()],
+            mask_columns: vec!["public.b.v".to_string()],
+            where_equals: vec!["public.c.id=1".to_string()],
+            ..base()
+        },
+        FilterConfig {
+            rename_schemas: vec![("public".to_string(), "tenant".to_string())],
+            lowercase_identifiers: true,
+            ..base()
+        },
+        FilterConfig {
+            to_inserts: true,
+            ..base()
+        },
+    ]
+}
+
+#[test]
+fn filtering_the_output_again_leaves_it_unchanged() {
+    let configs = configs();
+    for seed in 0..200 {
+        let dump = random_dump(&mut fastrand::Rng::with_seed(seed));
+        for (i, config) in configs.iter().enumerate() {
+            let once = output(&dump, config);
+            assert_eq!(output(&once, config), once, "seed {}, config {}", seed, i);
+        }
+    }
+}
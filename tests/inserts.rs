@@ -0,0 +1,116 @@
+mod common;
+
+use common::{dump, output};
+use pgdump_filter::FilterConfig;
+
+fn inserts() -> FilterConfig {
+    FilterConfig {
+        to_inserts: true,
+        ..FilterConfig::default()
+    }
+}
+
+#[test]
+fn rows_become_insert_statements() {
+    let dump = dump(&[("a", &["it's", "\\N", "tab\\there"])]);
+    let output = output(&dump, &inserts());
+    assert!(
+        output.contains(
+            "INSERT INTO public.a (v) VALUES ('it''s');\n\
+             INSERT INTO public.a (v) VALUES (NULL);\n\
+             INSERT INTO public.a (v) VALUES ('tab\there');\n"
+        ),
+        "{}",
+        output
+    );
+    assert!(!output.contains("COPY "), "{}", output);
+}
+
+#[test]
+fn an_empty_copy_block_leaves_no_empty_lines_behind() {
+    let dump = dump(&[("a", &["a1"]), ("b", &[])]);
+    let output = output(&dump, &inserts());
+    assert!(output.ends_with("VALUES ('a1');\n\n"), "{:?}", output);
+}
+
+/// The inserts of the output.
+fn batched(rows: &[&str], insert_batch: usize) -> String {
+    let config = FilterConfig {
+        insert_batch,
+        ..inserts()
+    };
+    let output = output(&dump(&[("a", rows)]), &config);
+    let start = output.find("INSERT ").unwrap();
+    output[start..]
+        .trim_end_matches('\n')
+        .lines()
+        .take_while(|line| line.starts_with("INSERT ") || line.starts_with('('))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn a_batch_of_one_is_an_insert_per_row() {
+    assert_eq!(
+        batched(&["1", "2"], 1),
+        "INSERT INTO public.a (v) VALUES ('1');\n\
+         INSERT INTO public.a (v) VALUES ('2');"
+    );
+}
+
+#[test]
+fn batches_are_full_but_the_last() {
+    assert_eq!(
+        batched(&["1", "2", "3", "4", "5"], 3),
+        "INSERT INTO public.a (v) VALUES ('1'),\n('2'),\n('3');\n\
+         INSERT INTO public.a (v) VALUES ('4'),\n('5');"
+    );
+    assert_eq!(
+        batched(&["1", "2", "3"], 3),
+        "INSERT INTO public.a (v) VALUES ('1'),\n('2'),\n('3');"
+    );
+}
+
+#[test]
+fn a_batch_over_the_row_count_is_ended_at_the_terminator() {
+    assert_eq!(
+        batched(&["1", "2"], 100),
+        "INSERT INTO public.a (v) VALUES ('1'),\n('2');"
+    );
+}
+
+const CUSTOM_NULL: &str = "COPY public.a (id, v) FROM stdin WITH (NULL 'NULL');\n\
+1\tNULL\n\
+2\t\\N\n\
+3\tx\n\
+\\.\n";
+
+#[test]
+fn the_null_string_is_a_null_in_the_inserts() {
+    let config = FilterConfig {
+        null_string: "NULL".to_string(),
+        ..inserts()
+    };
+    let output = output(CUSTOM_NULL, &config);
+    assert_eq!(
+        output,
+        "INSERT INTO public.a (id, v) VALUES ('1', NULL);\n\
+         INSERT INTO public.a (id, v) VALUES ('2', 'N');\n\
+         INSERT INTO public.a (id, v) VALUES ('3', 'x');\n"
+    );
+}
+
+#[test]
+fn the_null_string_stands_for_null_in_a_where_condition() {
+    let config = FilterConfig {
+        null_string: "NULL".to_string(),
+        where_equals: vec!["public.a.v=NULL".to_string()],
+        ..FilterConfig::default()
+    };
+    assert!(output(CUSTOM_NULL, &config).contains("stdin WITH (NULL 'NULL');\n1\tNULL\n\\.\n"));
+    let config = FilterConfig {
+        where_equals: vec!["public.a.v=\\N".to_string()],
+        ..FilterConfig::default()
+    };
+    assert!(output(CUSTOM_NULL, &config).contains("stdin WITH (NULL 'NULL');\n2\t\\N\n\\.\n"));
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use std::io::{self, BufReader, Read};
+
+use common::{dump, output};
+use pgdump_filter::{fill_buf, filter, FilterConfig};
+
+/// A reader interrupted by a signal before every read that returns data.
+struct Interrupted<R> {
+    inner: R,
+    interrupt: bool,
+}
+
+impl<R: Read> Read for Interrupted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.interrupt = !self.interrupt;
+        if self.interrupt {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+        let len = buf.len().min(7);
+        self.inner.read(&mut buf[..len])
+    }
+}
+
+fn interrupted(bytes: &[u8]) -> BufReader<Interrupted<&[u8]>> {
+    BufReader::with_capacity(
+        16,
+        Interrupted {
+            inner: bytes,
+            interrupt: false,
+        },
+    )
+}
+
+#[test]
+fn fill_buf_retries_interrupted_reads() {
+    assert_eq!(fill_buf(&mut interrupted(b"PGCOPY")).unwrap(), b"PGCOPY");
+}
+
+#[test]
+fn filtering_retries_interrupted_reads() {
+    let long_row = "x".repeat(100);
+    let dump = dump(&[("a", &["a1", &long_row]), ("b", &["b1"])]);
+    let mut filtered = Vec::new();
+    filter(
+        interrupted(dump.as_bytes()),
+        &mut filtered,
+        &FilterConfig::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        String::from_utf8(filtered).unwrap(),
+        output(&dump, &FilterConfig::default())
+    );
+}
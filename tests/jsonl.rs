@@ -0,0 +1,52 @@
+mod common;
+
+use common::{dump, output};
+use pgdump_filter::{filter, FilterConfig};
+
+fn jsonl() -> FilterConfig {
+    FilterConfig {
+        to_jsonl: true,
+        ..FilterConfig::default()
+    }
+}
+
+#[test]
+fn rows_become_objects_with_their_table() {
+    let dump = dump(&[("a", &["a1", "\\N"]), ("b", &["tab\\there"])]);
+    assert_eq!(
+        output(&dump, &jsonl()),
+        "{\"table\":\"public.a\",\"row\":{\"v\":\"a1\"}}\n\
+         {\"table\":\"public.a\",\"row\":{\"v\":null}}\n\
+         {\"table\":\"public.b\",\"row\":{\"v\":\"tab\\there\"}}\n"
+    );
+}
+
+#[test]
+fn a_byte_order_mark_is_not_kept() {
+    let dump = format!("\u{feff}{}", dump(&[("a", &["a1"])]));
+    let config = FilterConfig {
+        keep_bom: true,
+        ..jsonl()
+    };
+    assert_eq!(
+        output(&dump, &config),
+        "{\"table\":\"public.a\",\"row\":{\"v\":\"a1\"}}\n"
+    );
+}
+
+#[test]
+fn hooks_around_the_copy_blocks_are_rejected() {
+    let dump = dump(&[("a", &["a1"])]);
+    for config in [
+        FilterConfig {
+            before_block: Some("TRUNCATE %s;".to_string()),
+            ..jsonl()
+        },
+        FilterConfig {
+            after_block: Some("ANALYZE %s;".to_string()),
+            ..jsonl()
+        },
+    ] {
+        assert!(filter(dump.as_bytes(), std::io::sink(), &config).is_err());
+    }
+}
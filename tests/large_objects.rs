@@ -0,0 +1,133 @@
+mod common;
+
+use common::{filtered, output};
+use pgdump_filter::FilterConfig;
+
+const LARGE_OBJECTS: &str = "SELECT pg_catalog.lo_create('16397');\n\
+SELECT pg_catalog.lo_create('16398');\n\
+ALTER LARGE OBJECT 16397 OWNER TO me;\n\
+ALTER LARGE OBJECT 16398 OWNER TO me;\n\
+COMMENT ON LARGE OBJECT 16397 IS 'one\n\
+two';\n\
+GRANT ALL ON LARGE OBJECT 16397 TO me;\n\
+REVOKE ALL ON LARGE OBJECT 16398 FROM PUBLIC;\n\
+BEGIN;\n\
+SELECT pg_catalog.lo_open('16397', 131072);\n\
+SELECT pg_catalog.lowrite(0, '\\x41');\n\
+SELECT pg_catalog.lo_close(0);\n\
+COMMIT;\n";
+
+#[test]
+fn excluded_large_objects_lose_their_statements() {
+    let config = FilterConfig {
+        excluded_large_objects: vec![16397],
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        output(LARGE_OBJECTS, &config),
+        "SELECT pg_catalog.lo_create('16398');\n\
+         ALTER LARGE OBJECT 16398 OWNER TO me;\n\
+         REVOKE ALL ON LARGE OBJECT 16398 FROM PUBLIC;\n\
+         BEGIN;\n\
+         COMMIT;\n"
+    );
+}
+
+#[test]
+fn large_objects_are_kept_by_default() {
+    assert_eq!(
+        output(LARGE_OBJECTS, &FilterConfig::default()),
+        LARGE_OBJECTS
+    );
+}
+
+#[test]
+fn truncated_large_objects_are_created_empty() {
+    let config = FilterConfig {
+        truncate_large_objects: true,
+        ..FilterConfig::default()
+    };
+    let output = output(LARGE_OBJECTS, &config);
+    assert!(
+        output.contains("SELECT pg_catalog.lowrite(0, '');\n"),
+        "{}",
+        output
+    );
+    assert_eq!(
+        output.replace("lowrite(0, '')", "lowrite(0, '\\x41')"),
+        LARGE_OBJECTS
+    );
+}
+
+const CATALOG_COPY: &str = "SET x = 1;\n\
+COPY pg_catalog.pg_largeobject_metadata (oid, lomowner, lomacl) FROM stdin;\n\
+16397\t10\t\\N\n\
+\\.\n\
+COPY pg_catalog.pg_largeobject (loid, pageno, data) FROM stdin;\n\
+16397\t0\t\\\\x41\n\
+\\.\n\
+COPY public.t (v) FROM stdin;\n\
+t1\n\
+\\.\n";
+
+#[test]
+fn exclude_large_objects_drops_the_catalog_copy_blocks() {
+    let config = FilterConfig {
+        exclude_large_objects: true,
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        output(CATALOG_COPY, &config),
+        "SET x = 1;\nCOPY public.t (v) FROM stdin;\nt1\n\\.\n"
+    );
+    assert_eq!(output(CATALOG_COPY, &FilterConfig::default()), CATALOG_COPY);
+}
+
+#[test]
+fn truncate_large_objects_keeps_the_metadata_copy_block() {
+    let config = FilterConfig {
+        truncate_large_objects: true,
+        ..FilterConfig::default()
+    };
+    let output = output(CATALOG_COPY, &config);
+    assert!(output.contains("16397\t10\t\\N\n"), "{}", output);
+    assert!(
+        !output.contains("pg_catalog.pg_largeobject ("),
+        "{}",
+        output
+    );
+    assert!(output.contains("t1\n"), "{}", output);
+}
+
+#[test]
+fn the_large_object_functions_called_are_counted() {
+    let (_, stats) = filtered(LARGE_OBJECTS, &FilterConfig::default());
+    let functions: Vec<(&str, u64)> = stats
+        .large_object_functions
+        .iter()
+        .map(|(function, calls)| (function.as_str(), *calls))
+        .collect();
+    assert_eq!(
+        functions,
+        vec![
+            ("lo_close", 1),
+            ("lo_create", 2),
+            ("lo_open", 1),
+            ("lowrite", 1)
+        ]
+    );
+}
+
+#[test]
+fn strict_large_objects_fails_on_an_unknown_large_object_function() {
+    let dump = format!("{}SELECT pg_catalog.lo_truncate(0, 0);\n", LARGE_OBJECTS);
+    let config = FilterConfig {
+        strict_large_objects: true,
+        ..FilterConfig::default()
+    };
+    assert!(pgdump_filter::filter(LARGE_OBJECTS.as_bytes(), Vec::new(), &config).is_ok());
+    let error = pgdump_filter::filter(dump.as_bytes(), Vec::new(), &config).unwrap_err();
+    assert!(error.to_string().contains("lo_truncate"), "{}", error);
+    let (_, stats) = filtered(&dump, &FilterConfig::default());
+    assert_eq!(stats.large_object_functions["lo_truncate"], 1);
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use std::io::Cursor;
+
+use common::dump;
+use pgdump_filter::{filter, FilterConfig, FilteredLines, State};
+
+#[test]
+fn filter_reads_from_a_cursor_and_writes_to_a_vec() {
+    let dump = dump(&[("a", &["a1", "a2"]), ("b", &["b1"])]);
+    let config = FilterConfig {
+        excluded_copy_blocks: vec!["b".to_string()],
+        ..FilterConfig::default()
+    };
+    let mut output = Vec::new();
+    let stats = filter(Cursor::new(dump.as_bytes()), &mut output, &config).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(
+        output.contains("COPY public.a (v) FROM stdin;\na1\na2\n\\.\n"),
+        "{}",
+        output
+    );
+    assert!(!output.contains("b1"), "{}", output);
+    let blocks: Vec<_> = stats
+        .copy_blocks
+        .iter()
+        .map(|block| (block.name.as_str(), block.included, block.rows))
+        .collect();
+    assert_eq!(blocks, vec![("public.a", true, 2), ("public.b", false, 1)]);
+    assert_eq!(stats.included[&State::IncludedCopyBlock].lines, 3);
+}
+
+#[test]
+fn filter_fails_on_an_invalid_pattern() {
+    let config = FilterConfig {
+        regex: true,
+        included_copy_blocks: vec!["public.(".to_string()],
+        ..FilterConfig::default()
+    };
+    assert!(filter(Cursor::new(b""), Vec::new(), &config).is_err());
+}
+
+#[test]
+fn filtered_lines_are_the_lines_filter_writes() {
+    let dump = dump(&[("a", &["a1", "a2"]), ("b", &["b1"]), ("c", &[])]);
+    let configs = [
+        FilterConfig::default(),
+        FilterConfig {
+            excluded_copy_blocks: vec!["b".to_string()],
+            keep_comments: true,
+            ..FilterConfig::default()
+        },
+        FilterConfig {
+            to_inserts: true,
+            ..FilterConfig::default()
+        },
+    ];
+    for config in &configs {
+        let mut lines = FilteredLines::new(dump.as_bytes(), config);
+        let collected: Vec<Vec<u8>> = lines.by_ref().collect::<Result<_, _>>().unwrap();
+        let (output, stats) = common::filtered(&dump, config);
+        assert_eq!(String::from_utf8(collected.concat()).unwrap(), output);
+        assert!(collected.iter().all(|line| line.ends_with(b"\n")));
+        assert_eq!(lines.stats(), Some(&stats));
+    }
+}
+
+#[test]
+fn filtered_lines_yield_an_invalid_config_as_the_only_error() {
+    let config = FilterConfig {
+        regex: true,
+        included_copy_blocks: vec!["public.(".to_string()],
+        ..FilterConfig::default()
+    };
+    let mut lines = FilteredLines::new(&b"SELECT 1;\n"[..], &config);
+    assert!(lines.next().unwrap().is_err());
+    assert!(lines.next().is_none());
+    assert!(lines.stats().is_none());
+}
+
+#[test]
+fn errors_say_at_which_line_of_the_dump_they_happened() {
+    let dump =
+        "SELECT 1;\nSELECT 2;\nCOPY public.a (v) FROM stdin;\nshort\nthis row is much too long for it\n\\.\n";
+    let config = FilterConfig {
+        max_line_bytes: Some(30),
+        ..FilterConfig::default()
+    };
+    let error = filter(dump.as_bytes(), Vec::new(), &config).unwrap_err();
+    assert!(error.to_string().starts_with("line 5: "), "{}", error);
+}
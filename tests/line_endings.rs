@@ -0,0 +1,31 @@
+mod common;
+
+use common::output;
+use pgdump_filter::FilterConfig;
+
+const CRLF: &str = "SET x = 1;\r\nCOPY public.a (v) FROM stdin;\r\na1\r\n\\.\r\n\r\n\r\n\
+COPY public.b (v) FROM stdin;\r\nb1\r\n\\.\r\nSELECT 1;\r\n";
+
+#[test]
+fn crlf_lines_are_told_apart_like_lf_ones() {
+    let config = FilterConfig {
+        excluded_copy_blocks: vec!["a".to_string()],
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        output(CRLF, &config),
+        "SET x = 1;\r\n\r\nCOPY public.b (v) FROM stdin;\r\nb1\r\n\\.\r\nSELECT 1;\r\n"
+    );
+}
+
+#[test]
+fn the_last_line_needs_no_newline() {
+    let dump = "SET x = 1;\nCOPY public.a (v) FROM stdin;\na1\n\\.";
+    assert_eq!(output(dump, &FilterConfig::default()), dump);
+    let config = FilterConfig {
+        excluded_copy_blocks: vec!["a".to_string()],
+        ..FilterConfig::default()
+    };
+    assert_eq!(output(dump, &config), "SET x = 1;\n");
+    assert_eq!(output("SELECT 1;", &FilterConfig::default()), "SELECT 1;");
+}
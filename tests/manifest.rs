@@ -0,0 +1,43 @@
+mod common;
+
+use pgdump_filter::{FilterConfig, ManifestEntry, Reason};
+
+#[test]
+fn the_manifest_lists_every_table_with_why_it_was_included_or_excluded() {
+    let dump = format!(
+        "{}COPY \"Odd\" (v) FROM stdin;\no1\n\\.\n\
+         COPY pg_catalog.pg_largeobject (loid, pageno, data) FROM stdin;\n\\.\n",
+        common::dump(&[("a", &["a1", "a2"]), ("b", &["b1"])])
+    );
+    let config = FilterConfig {
+        excluded_copy_blocks: vec!["b".to_string()],
+        exclude_large_objects: true,
+        max_rows: Some(1),
+        allow_unqualified: true,
+        ..FilterConfig::default()
+    };
+    let (_, stats) = common::filtered(&dump, &config);
+    let entry = |schema: Option<&str>, table: &str, reason, rows, included_rows| ManifestEntry {
+        schema: schema.map(str::to_string),
+        table: table.to_string(),
+        included: reason == Reason::Included,
+        reason,
+        rows,
+        included_rows,
+    };
+    assert_eq!(
+        stats.manifest(),
+        vec![
+            entry(Some("public"), "a", Reason::Included, 2, 1),
+            entry(Some("public"), "b", Reason::Excluded, 1, 0),
+            entry(None, "Odd", Reason::Included, 1, 1),
+            entry(
+                Some("pg_catalog"),
+                "pg_largeobject",
+                Reason::LargeObjectData,
+                0,
+                0
+            ),
+        ]
+    );
+}
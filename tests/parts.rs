@@ -0,0 +1,99 @@
+mod common;
+
+use std::io::Cursor;
+
+use common::{dump, output, output_in_parts};
+use pgdump_filter::{copy_block_boundary, FilterConfig};
+
+/// Rows of a table that read like the lines around a copy block header.
+const TRICKY_ROWS: &[&str] = &[
+    "row1",
+    "COPY secret leak",
+    "COPY public.b (v) FROM stdin;",
+    "-- dashdash",
+    "",
+    "row4",
+];
+
+#[test]
+fn rows_looking_like_headers_are_no_boundaries() {
+    let dump = dump(&[("a", TRICKY_ROWS), ("b", &["b1", "b2"]), ("c", &["c1"])]);
+    let config = FilterConfig {
+        excluded_copy_blocks: vec!["a".to_string()],
+        ..FilterConfig::default()
+    };
+    let serial = output(&dump, &config);
+    assert!(!serial.contains("leak"));
+    for jobs in 2..=8 {
+        assert_eq!(
+            output_in_parts(&dump, &config, jobs),
+            serial,
+            "{} jobs",
+            jobs
+        );
+    }
+}
+
+#[test]
+fn boundaries_are_at_headers_after_a_table_of_contents_entry() {
+    let dump = dump(&[("a", TRICKY_ROWS), ("b", &["b1"])]);
+    let start_of_rows = dump.find("row1").unwrap() as u64;
+    let header_of_b = dump.find("COPY public.b (v) FROM stdin;\nb1").unwrap() as u64;
+    let mut cursor = Cursor::new(dump.as_bytes());
+    assert_eq!(copy_block_boundary(&mut cursor, 0).unwrap(), 0);
+    assert_eq!(
+        copy_block_boundary(&mut cursor, start_of_rows).unwrap(),
+        header_of_b
+    );
+    assert_eq!(
+        copy_block_boundary(&mut cursor, header_of_b + 1).unwrap(),
+        dump.len() as u64
+    );
+}
+
+#[test]
+fn a_dump_without_table_of_contents_has_no_boundaries() {
+    let dump = "SET x = 1;\nSET y = 2;\nCOPY public.a (v) FROM stdin;\n1\n2\n3\n\\.\n\
+                COPY public.b (v) FROM stdin;\n4\n\\.\n";
+    let mut cursor = Cursor::new(dump.as_bytes());
+    assert!(copy_block_boundary(&mut cursor, 5).is_err());
+}
+
+#[test]
+fn parts_give_the_output_of_the_whole_dump() {
+    let rows: Vec<String> = (0..50).map(|i| format!("{}", i)).collect();
+    let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+    let dump = dump(&[("a", &rows), ("b", &rows), ("c", &rows), ("d", &rows)]);
+    let configs = [
+        FilterConfig::default(),
+        FilterConfig {
+            excluded_copy_blocks: vec!["b".to_string()],
+            ..FilterConfig::default()
+        },
+        FilterConfig {
+            included_copy_blocks: vec!["c".to_string()],
+            keep_comments: true,
+            ..FilterConfig::default()
+        },
+        FilterConfig {
+            max_rows: Some(3),
+            ..FilterConfig::default()
+        },
+        FilterConfig {
+            excluded_copy_blocks: vec!["b".to_string()],
+            keep_blank_lines: true,
+            ..FilterConfig::default()
+        },
+    ];
+    for config in &configs {
+        let serial = output(&dump, config);
+        for jobs in 1..=8 {
+            assert_eq!(
+                output_in_parts(&dump, config, jobs),
+                serial,
+                "{} jobs",
+                jobs
+            );
+        }
+    }
+}
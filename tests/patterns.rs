@@ -0,0 +1,255 @@
+mod common;
+
+use common::{dump, filtered};
+use pgdump_filter::FilterConfig;
+
+/// The names of the included copy blocks of the dump of these tables.
+fn included(tables: &[&str], config: &FilterConfig) -> Vec<String> {
+    let tables: Vec<(&str, &[&str])> = tables.iter().map(|&table| (table, &[][..])).collect();
+    let (_, stats) = filtered(&dump(&tables), config);
+    stats
+        .copy_blocks
+        .into_iter()
+        .filter(|block| block.included)
+        .map(|block| block.name)
+        .collect()
+}
+
+fn strings(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+#[test]
+fn globs_match_the_table_names() {
+    let config = FilterConfig {
+        excluded_copy_blocks: strings(&["log_*", "t?"]),
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        included(&["log_a", "log_b", "t1", "t12", "users"], &config),
+        strings(&["public.t12", "public.users"])
+    );
+}
+
+#[test]
+fn regular_expressions_match_the_table_names() {
+    let config = FilterConfig {
+        regex: true,
+        included_copy_blocks: strings(&["^log_[0-9]+$"]),
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        included(&["log_1", "log_22", "log_x", "catalog_1"], &config),
+        strings(&["public.log_1", "public.log_22"])
+    );
+}
+
+/// A dump of nothing but an empty copy block for each of the qualified names.
+fn copy_blocks(names: &[&str]) -> String {
+    names
+        .iter()
+        .map(|name| format!("COPY {} (v) FROM stdin;\n\\.\n", name))
+        .collect()
+}
+
+/// The names of the included copy blocks of the dump of these qualified names.
+fn included_blocks(names: &[&str], config: &FilterConfig) -> Vec<String> {
+    let (_, stats) = filtered(&copy_blocks(names), config);
+    stats
+        .copy_blocks
+        .into_iter()
+        .filter(|block| block.included)
+        .map(|block| block.name)
+        .collect()
+}
+
+#[test]
+fn copy_blocks_match_in_every_schema_given() {
+    let config = FilterConfig {
+        included_copy_blocks: strings(&["users"]),
+        schemas: strings(&["sales", "hr"]),
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        included_blocks(
+            &["public.users", "sales.users", "hr.users", "hr.other"],
+            &config
+        ),
+        strings(&["sales.users", "hr.users"])
+    );
+}
+
+#[test]
+fn case_sensitive_tells_names_apart_by_case() {
+    let names = ["public.\"Users\"", "public.users"];
+    let ignoring_case = FilterConfig {
+        included_copy_blocks: strings(&["USERS"]),
+        ..FilterConfig::default()
+    };
+    assert_eq!(included_blocks(&names, &ignoring_case), strings(&names));
+    let case_sensitive = FilterConfig {
+        included_copy_blocks: strings(&["Users"]),
+        case_sensitive: true,
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        included_blocks(&names, &case_sensitive),
+        strings(&["public.\"Users\""])
+    );
+}
+
+#[test]
+fn quoted_names_match_without_their_quotes() {
+    let names = [
+        "public.\"user\"",
+        "public.\"order items\"",
+        "public.\"a\"\"b\"",
+    ];
+    let config = FilterConfig {
+        included_copy_blocks: strings(&["user", "\"order items\"", "a\"b"]),
+        ..FilterConfig::default()
+    };
+    assert_eq!(included_blocks(&names, &config), strings(&names));
+}
+
+#[test]
+fn invert_turns_the_included_copy_blocks_into_excluded_ones() {
+    let config = FilterConfig {
+        included_copy_blocks: strings(&["a"]),
+        invert: true,
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        included(&["a", "b", "c"], &config),
+        strings(&["public.b", "public.c"])
+    );
+}
+
+#[test]
+fn a_copy_block_both_included_and_excluded_is_rejected() {
+    let config = FilterConfig {
+        included_copy_blocks: strings(&["a", "b"]),
+        excluded_copy_blocks: strings(&["\"B\""]),
+        ..FilterConfig::default()
+    };
+    let error = pgdump_filter::filter(&b""[..], std::io::sink(), &config).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "copy block 'b' is both included and excluded"
+    );
+}
+
+#[test]
+fn unqualified_copy_blocks_match_only_with_allow_unqualified() {
+    let names = ["users", "public.users", "\"Orders\"", "log"];
+    let included = FilterConfig {
+        included_copy_blocks: strings(&["users", "Orders"]),
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        included_blocks(&names, &included),
+        strings(&["public.users"])
+    );
+    let included = FilterConfig {
+        allow_unqualified: true,
+        ..included
+    };
+    assert_eq!(
+        included_blocks(&names, &included),
+        strings(&["users", "public.users", "\"Orders\""])
+    );
+    let excluded = FilterConfig {
+        excluded_copy_blocks: strings(&["users"]),
+        allow_unqualified: true,
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        included_blocks(&names, &excluded),
+        strings(&["\"Orders\"", "log"])
+    );
+}
+
+#[test]
+fn max_blocks_keeps_the_first_copy_blocks_after_the_other_patterns() {
+    let tables = ["a", "b", "c", "d", "e"];
+    let config = FilterConfig {
+        max_blocks: Some(2),
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        included(&tables, &config),
+        strings(&["public.a", "public.b"])
+    );
+    let config = FilterConfig {
+        max_blocks: Some(2),
+        excluded_copy_blocks: strings(&["a"]),
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        included(&tables, &config),
+        strings(&["public.b", "public.c"])
+    );
+    let dump = dump(&[("a", &["a1"]), ("b", &["b1"]), ("c", &["c1"])]);
+    let config = FilterConfig {
+        max_blocks: Some(1),
+        ..FilterConfig::default()
+    };
+    let (output, stats) = filtered(&dump, &config);
+    assert!(output.contains("CREATE TABLE public.c"), "{}", output);
+    assert!(
+        !output.contains("b1") && !output.contains("c1"),
+        "{}",
+        output
+    );
+    assert_eq!(
+        stats.copy_blocks[2].reason,
+        pgdump_filter::Reason::MaxBlocks
+    );
+}
+
+#[test]
+fn prefix_match_matches_the_partitions_of_a_table() {
+    let tables = [
+        "events",
+        "events_2023",
+        "events_2024",
+        "events_archive",
+        "my_events",
+    ];
+    let config = FilterConfig {
+        included_copy_blocks: strings(&["events"]),
+        ..FilterConfig::default()
+    };
+    assert_eq!(included(&tables, &config), strings(&["public.events"]));
+    let config = FilterConfig {
+        prefix_match: true,
+        ..config
+    };
+    assert_eq!(
+        included(&tables, &config),
+        strings(&[
+            "public.events",
+            "public.events_2023",
+            "public.events_2024",
+            "public.events_archive"
+        ])
+    );
+    let config = FilterConfig {
+        excluded_copy_blocks: strings(&["events_archive"]),
+        ..config
+    };
+    assert_eq!(
+        included(&tables, &config),
+        strings(&["public.events", "public.events_2023", "public.events_2024"])
+    );
+    // globs match as they are
+    let config = FilterConfig {
+        included_copy_blocks: strings(&["events_202?"]),
+        prefix_match: true,
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        included(&tables, &config),
+        strings(&["public.events_2023", "public.events_2024"])
+    );
+}
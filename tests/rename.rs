@@ -0,0 +1,109 @@
+mod common;
+
+use common::output;
+use pgdump_filter::FilterConfig;
+
+const MIXED_CASE: &str = "CREATE SCHEMA \"Sales\";\n\
+SET search_path = \"Sales\", pg_catalog;\n\
+CREATE TABLE \"Sales\".\"Order\" (\n    \"Amount\" integer,\n    Note text DEFAULT 'Mixed \"Case\"'\n);\n\
+SELECT pg_catalog.setval('\"Sales\".\"Order_id_seq\"', 1, true);\n\
+COPY \"Sales\".\"Order\" (\"Amount\", Note) FROM stdin;\n\
+1\tAbC \"X\"\n\
+\\.\n";
+
+#[test]
+fn lowercase_identifiers_folds_quoted_and_qualified_names() {
+    let config = FilterConfig {
+        lowercase_identifiers: true,
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        output(MIXED_CASE, &config),
+        "CREATE SCHEMA \"sales\";\n\
+         SET search_path = \"sales\", pg_catalog;\n\
+         CREATE TABLE \"sales\".\"order\" (\n    \"amount\" integer,\n    Note text DEFAULT 'Mixed \"Case\"'\n);\n\
+         SELECT pg_catalog.setval('\"sales\".\"order_id_seq\"', 1, true);\n\
+         COPY \"sales\".\"order\" (\"amount\", Note) FROM stdin;\n\
+         1\tAbC \"X\"\n\
+         \\.\n"
+    );
+}
+
+#[test]
+fn lowercase_identifiers_leaves_keywords_and_rows_alone() {
+    let dump = "CREATE TABLE t (\n    V text\n);\nCOPY t (V) FROM stdin;\nMiXeD\n\\.\n";
+    let config = FilterConfig {
+        lowercase_identifiers: true,
+        ..FilterConfig::default()
+    };
+    assert_eq!(output(dump, &config), dump);
+}
+
+#[test]
+fn renamed_schemas_and_tables_are_rewritten_in_statements_and_headers() {
+    let dump = "SET search_path = public, pg_catalog;\n\
+                CREATE TABLE public.foo (\n    v text\n);\n\
+                CREATE TABLE public.foobar (\n    v text\n);\n\
+                COPY public.foo (v) FROM stdin;\npublic.foo\n\\.\n";
+    let config = FilterConfig {
+        rename_schemas: vec![("public".to_string(), "tenant".to_string())],
+        rename_tables: vec![("public.foo".to_string(), "bar".to_string())],
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        output(dump, &config),
+        "SET search_path = tenant, pg_catalog;\n\
+         CREATE TABLE tenant.bar (\n    v text\n);\n\
+         CREATE TABLE tenant.foobar (\n    v text\n);\n\
+         COPY tenant.bar (v) FROM stdin;\npublic.foo\n\\.\n"
+    );
+}
+
+#[test]
+fn renamed_schemas_match_quoted_and_unquoted_names_only_before_a_dot() {
+    let dump = "CREATE SCHEMA public;\n\
+                ALTER TABLE ONLY \"public\".a ADD CONSTRAINT public_pkey PRIMARY KEY (id);\n\
+                COPY PUBLIC.a (v) FROM stdin;\npublic.a\n\\.\n";
+    let config = FilterConfig {
+        rename_schemas: vec![("public".to_string(), "tenant".to_string())],
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        output(dump, &config),
+        "CREATE SCHEMA public;\n\
+         ALTER TABLE ONLY tenant.a ADD CONSTRAINT public_pkey PRIMARY KEY (id);\n\
+         COPY tenant.a (v) FROM stdin;\npublic.a\n\\.\n"
+    );
+}
+
+#[test]
+fn renamed_tables_keep_their_schema_and_rows() {
+    let dump = "CREATE TABLE sales.orders (\n    v text\n);\n\
+                ALTER TABLE ONLY sales.orders ADD CONSTRAINT orders_pkey PRIMARY KEY (v);\n\
+                CREATE TABLE public.orders (\n    v text\n);\n\
+                CREATE TABLE sales.orders_archive (\n    v text\n);\n\
+                COPY sales.orders (v) FROM stdin;\nsales.orders\n\\.\n\
+                COPY sales.orders_archive (v) FROM stdin;\na1\n\\.\n";
+    let config = FilterConfig {
+        rename_tables: vec![("sales.orders".to_string(), "orders_staging".to_string())],
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        output(dump, &config),
+        "CREATE TABLE sales.orders_staging (\n    v text\n);\n\
+         ALTER TABLE ONLY sales.orders_staging ADD CONSTRAINT orders_pkey PRIMARY KEY (v);\n\
+         CREATE TABLE public.orders (\n    v text\n);\n\
+         CREATE TABLE sales.orders_archive (\n    v text\n);\n\
+         COPY sales.orders_staging (v) FROM stdin;\nsales.orders\n\\.\n\
+         COPY sales.orders_archive (v) FROM stdin;\na1\n\\.\n"
+    );
+}
+
+#[test]
+fn renaming_a_table_without_a_schema_is_rejected() {
+    let config = FilterConfig {
+        rename_tables: vec![("orders".to_string(), "staging".to_string())],
+        ..FilterConfig::default()
+    };
+    assert!(pgdump_filter::filter(&b""[..], Vec::new(), &config).is_err());
+}
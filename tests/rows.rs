@@ -0,0 +1,204 @@
+mod common;
+
+use common::{dump, filtered, output};
+use pgdump_filter::FilterConfig;
+
+/// The rows of every copy block of the output, in order.
+fn rows(output: &str) -> Vec<Vec<&str>> {
+    let mut blocks = vec![];
+    let mut block: Option<Vec<&str>> = None;
+    for line in output.lines() {
+        match block.take() {
+            Some(rows) if line == "\\." => blocks.push(rows),
+            Some(mut rows) => {
+                rows.push(line);
+                block = Some(rows);
+            }
+            None if line.starts_with("COPY ") => block = Some(vec![]),
+            None => {}
+        }
+    }
+    blocks
+}
+
+#[test]
+fn max_rows_keeps_the_first_rows_of_every_copy_block() {
+    let dump = dump(&[("a", &["a1", "a2", "a3"]), ("b", &["b1"])]);
+    let config = FilterConfig {
+        max_rows: Some(2),
+        ..FilterConfig::default()
+    };
+    let (output, stats) = filtered(&dump, &config);
+    assert_eq!(rows(&output), vec![vec!["a1", "a2"], vec!["b1"]]);
+    assert_eq!(stats.copy_blocks[0].rows, 3);
+    assert_eq!(stats.copy_blocks[0].included_rows, 2);
+}
+
+#[test]
+fn max_rows_zero_keeps_the_copy_blocks_empty() {
+    let dump = dump(&[("a", &["a1"])]);
+    let config = FilterConfig {
+        max_rows: Some(0),
+        ..FilterConfig::default()
+    };
+    let output = output(&dump, &config);
+    assert!(
+        output.contains("COPY public.a (v) FROM stdin;\n\\.\n"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn sample_keeps_the_same_rows_for_the_same_seed() {
+    let rows_of_a: Vec<String> = (0..200).map(|i| i.to_string()).collect();
+    let rows_of_a: Vec<&str> = rows_of_a.iter().map(String::as_str).collect();
+    let dump = dump(&[("a", &rows_of_a)]);
+    let sampled = |seed| {
+        let config = FilterConfig {
+            sample: Some(0.5),
+            seed: Some(seed),
+            ..FilterConfig::default()
+        };
+        output(&dump, &config)
+    };
+    let kept = rows(&sampled(1))[0].len();
+    assert!(kept > 50 && kept < 150, "{} rows kept", kept);
+    assert_eq!(sampled(1), sampled(1));
+    assert_ne!(sampled(1), sampled(2));
+}
+
+#[test]
+fn sample_zero_and_one_keep_no_rows_and_all_rows() {
+    let dump = dump(&[("a", &["a1", "a2"])]);
+    let sampled = |fraction| {
+        let config = FilterConfig {
+            sample: Some(fraction),
+            ..FilterConfig::default()
+        };
+        output(&dump, &config)
+    };
+    assert_eq!(rows(&sampled(0.0)), vec![Vec::<&str>::new()]);
+    assert_eq!(rows(&sampled(1.0)), vec![vec!["a1", "a2"]]);
+}
+
+const TENANTS: &str = "COPY public.orders (id, tenant_id, note) FROM stdin;\n\
+1\t7\tfirst\n\
+2\t8\tsecond\n\
+3\t7\t\\N\n\
+4\t\\N\tnull tenant\n\
+5\t7\ta\\tb\n\
+\\.\n\
+COPY public.tenants (tenant_id) FROM stdin;\n\
+7\n\
+8\n\
+\\.\n";
+
+fn wheres(conditions: &[&str]) -> FilterConfig {
+    FilterConfig {
+        where_equals: conditions.iter().map(|c| c.to_string()).collect(),
+        ..FilterConfig::default()
+    }
+}
+
+#[test]
+fn where_equals_keeps_the_rows_with_the_value_in_the_column() {
+    let output = output(TENANTS, &wheres(&["public.orders.tenant_id=7"]));
+    assert_eq!(
+        rows(&output),
+        vec![
+            vec!["1\t7\tfirst", "3\t7\t\\N", "5\t7\ta\\tb"],
+            vec!["7", "8"]
+        ]
+    );
+    assert!(output.ends_with("\\.\n"), "{}", output);
+}
+
+#[test]
+fn where_equals_conditions_on_a_table_all_hold() {
+    let kept = output(
+        TENANTS,
+        &wheres(&["public.orders.tenant_id=7", "public.orders.note=\\N"]),
+    );
+    assert_eq!(rows(&kept)[0], vec!["3\t7\t\\N"]);
+    let kept = output(TENANTS, &wheres(&["public.orders.tenant_id=\\N"]));
+    assert_eq!(rows(&kept)[0], vec!["4\t\\N\tnull tenant"]);
+    let kept = output(TENANTS, &wheres(&["public.orders.note=a\tb"]));
+    assert_eq!(rows(&kept)[0], vec!["5\t7\ta\\tb"]);
+}
+
+#[test]
+fn where_in_keeps_the_rows_with_any_of_the_values() {
+    let dump = "COPY public.files (id, path) FROM stdin;\n\
+                1\tC:\\\\temp\n\
+                2\t/tmp\n\
+                3\t/home\n\
+                4\t/var\n\
+                \\.\n";
+    let config = FilterConfig {
+        where_in: vec!["public.files.path=/tmp,C:\\temp,/var".to_string()],
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        rows(&output(dump, &config)),
+        vec![vec!["1\tC:\\\\temp", "2\t/tmp", "4\t/var"]]
+    );
+    let config = FilterConfig {
+        where_in: vec!["public.files.id=1,2,3".to_string()],
+        where_equals: vec!["public.files.path=/home".to_string()],
+        ..FilterConfig::default()
+    };
+    assert_eq!(rows(&output(dump, &config)), vec![vec!["3\t/home"]]);
+}
+
+#[test]
+fn a_copy_block_without_rows_left_is_still_well_formed() {
+    let config = FilterConfig {
+        where_equals: vec!["public.orders.tenant_id=9".to_string()],
+        ..FilterConfig::default()
+    };
+    let kept = output(TENANTS, &config);
+    assert!(
+        kept.starts_with("COPY public.orders (id, tenant_id, note) FROM stdin;\n\\.\n"),
+        "{}",
+        kept
+    );
+    let config = FilterConfig {
+        sample: Some(0.0),
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        output(TENANTS, &config),
+        "COPY public.orders (id, tenant_id, note) FROM stdin;\n\\.\n\
+         COPY public.tenants (tenant_id) FROM stdin;\n\\.\n"
+    );
+}
+
+#[test]
+fn a_truncated_table_keeps_its_definition_and_an_empty_copy_block() {
+    let dump = dump(&[("a", &["a1", "a2"]), ("b", &["b1"])]);
+    let config = FilterConfig {
+        truncated_tables: vec!["a".to_string()],
+        ..FilterConfig::default()
+    };
+    let (truncated, stats) = filtered(&dump, &config);
+    assert!(
+        truncated.contains("CREATE TABLE public.a (\n    v text\n);\n"),
+        "{}",
+        truncated
+    );
+    assert_eq!(
+        truncated,
+        output(&dump, &FilterConfig::default()).replace(
+            "COPY public.a (v) FROM stdin;\na1\na2\n\\.\n",
+            "COPY public.a (v) FROM stdin;\n\\.\n"
+        )
+    );
+    assert_eq!(rows(&truncated), vec![vec![], vec!["b1"]]);
+    let rows: Vec<(u64, u64)> = stats
+        .copy_blocks
+        .iter()
+        .map(|block| (block.rows, block.included_rows))
+        .collect();
+    assert_eq!(rows, [(2, 0), (1, 1)]);
+}
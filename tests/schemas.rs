@@ -0,0 +1,31 @@
+mod common;
+
+use common::{dump, filtered};
+use pgdump_filter::FilterConfig;
+
+#[test]
+fn schemas_without_copy_blocks_are_unmatched() {
+    let config = FilterConfig {
+        included_copy_blocks: vec!["a".to_string()],
+        schemas: vec!["public".to_string(), "Sales".to_string()],
+        ..FilterConfig::default()
+    };
+    let (output, stats) = filtered(&dump(&[("a", &["a1"]), ("b", &["b1"])]), &config);
+    assert_eq!(stats.unmatched_schemas, vec!["Sales".to_string()]);
+    assert!(
+        output.contains("a1") && !output.contains("b1"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn schemas_match_ignoring_case() {
+    let config = FilterConfig {
+        included_copy_blocks: vec!["a".to_string()],
+        schemas: vec!["PUBLIC".to_string()],
+        ..FilterConfig::default()
+    };
+    let (_, stats) = filtered(&dump(&[("a", &["a1"])]), &config);
+    assert!(stats.unmatched_schemas.is_empty());
+}
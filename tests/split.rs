@@ -0,0 +1,25 @@
+mod common;
+
+use std::fs;
+
+use common::{dump, output};
+use pgdump_filter::FilterConfig;
+
+#[test]
+fn copy_blocks_of_the_same_table_go_to_one_file() {
+    let dir = std::env::temp_dir().join(format!("pgdump_filter_split_{}", std::process::id()));
+    let config = FilterConfig {
+        split_dir: Some(dir.clone()),
+        ..FilterConfig::default()
+    };
+    output(
+        &dump(&[("a", &["a1"]), ("b", &["b1"]), ("a", &["a2"])]),
+        &config,
+    );
+    let table = fs::read_to_string(dir.join("public.a.sql")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        table,
+        "COPY public.a (v) FROM stdin;\na1\n\\.\nCOPY public.a (v) FROM stdin;\na2\n\\.\n"
+    );
+}
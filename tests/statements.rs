@@ -0,0 +1,288 @@
+mod common;
+
+use common::{dump, filtered, output};
+use pgdump_filter::{FilterConfig, State};
+
+#[test]
+fn keep_comments_keeps_the_comments_outside_the_copy_blocks() {
+    let dump = dump(&[("a", &["a1"])]);
+    let config = FilterConfig {
+        keep_comments: true,
+        ..FilterConfig::default()
+    };
+    let output = output(&dump, &config);
+    assert!(
+        output.starts_with("--\n-- PostgreSQL database dump\n--\n"),
+        "{}",
+        output
+    );
+    assert!(output.contains("-- Data for Name: a;"), "{}", output);
+    assert!(!output.contains("\n\n\n"), "{}", output);
+}
+
+#[test]
+fn keep_blank_lines_keeps_the_consecutive_empty_lines() {
+    let dump = "SET x = 1;\n\n\n\nSELECT 1;\n";
+    let config = FilterConfig {
+        keep_blank_lines: true,
+        ..FilterConfig::default()
+    };
+    assert_eq!(output(dump, &config), dump);
+    assert_eq!(
+        output(dump, &FilterConfig::default()),
+        "SET x = 1;\n\nSELECT 1;\n"
+    );
+}
+
+const STATEMENTS: &[&str] = &[
+    "SET x = 1;",
+    "SELECT pg_catalog.set_config('search_path', '', false);",
+    "SET search_path = public;",
+    "GRANT ALL ON TABLE public.a TO me;",
+    "REVOKE ALL ON SCHEMA public FROM PUBLIC;",
+    "ALTER TABLE public.a OWNER TO me;",
+    "ALTER SEQUENCE public.s OWNER TO me;",
+    "ALTER TABLE ONLY public.a ADD CONSTRAINT p PRIMARY KEY (id);",
+];
+
+/// The statements left of [`STATEMENTS`] after filtering them.
+fn statements_kept(config: &FilterConfig) -> Vec<String> {
+    let dump: String = STATEMENTS.iter().map(|s| format!("{}\n", s)).collect();
+    output(&dump, config).lines().map(String::from).collect()
+}
+
+/// [`STATEMENTS`] without the ones at these positions.
+fn statements_without(dropped: &[usize]) -> Vec<String> {
+    (0..STATEMENTS.len())
+        .filter(|i| !dropped.contains(i))
+        .map(|i| STATEMENTS[i].to_string())
+        .collect()
+}
+
+#[test]
+fn strip_set_and_strip_search_path_drop_the_settings() {
+    let strip_set = FilterConfig {
+        strip_set: true,
+        ..FilterConfig::default()
+    };
+    assert_eq!(statements_kept(&strip_set), statements_without(&[0]));
+    let strip_search_path = FilterConfig {
+        strip_search_path: true,
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        statements_kept(&strip_search_path),
+        statements_without(&[1, 2])
+    );
+}
+
+#[test]
+fn strip_grants_drops_the_privileges() {
+    let config = FilterConfig {
+        strip_grants: true,
+        ..FilterConfig::default()
+    };
+    assert_eq!(statements_kept(&config), statements_without(&[3, 4]));
+}
+
+#[test]
+fn strip_owner_drops_the_owners_but_not_the_other_alter_statements() {
+    let config = FilterConfig {
+        strip_owner: true,
+        ..FilterConfig::default()
+    };
+    assert_eq!(statements_kept(&config), statements_without(&[5, 6]));
+}
+
+const SCHEMA_AND_DATA: &str = "CREATE TABLE public.a (v text);\n\
+COPY public.a (v) FROM stdin;\na1\n\\.\n\
+SELECT pg_catalog.setval('public.s', 1, true);\n";
+
+#[test]
+fn schema_only_keeps_the_statements_without_the_data() {
+    let config = FilterConfig {
+        schema_only: true,
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        output(SCHEMA_AND_DATA, &config),
+        "CREATE TABLE public.a (v text);\n"
+    );
+}
+
+#[test]
+fn data_only_keeps_the_data_without_the_statements() {
+    let config = FilterConfig {
+        data_only: true,
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        output(SCHEMA_AND_DATA, &config),
+        "COPY public.a (v) FROM stdin;\na1\n\\.\n\
+         SELECT pg_catalog.setval('public.s', 1, true);\n"
+    );
+}
+
+const FUNCTION: &str =
+    "CREATE FUNCTION public.f() RETURNS void\n    LANGUAGE plpgsql\n    AS $_$\n\
+-- not a comment of the dump\n\n\n\
+BEGIN\n    COPY public.a (v) FROM stdin;\n    RAISE NOTICE '$$ -- ;';\nEND;\n$_$;\n";
+
+#[test]
+fn dollar_quoted_bodies_are_kept_verbatim() {
+    let dump = format!("{}SELECT 1;\n", FUNCTION);
+    assert_eq!(output(&dump, &FilterConfig::default()), dump);
+}
+
+#[test]
+fn dollar_quoted_bodies_go_with_their_statement() {
+    let dump = format!("{}SELECT 1;\n", FUNCTION);
+    let config = FilterConfig {
+        excluded_statements: vec!["CREATE FUNCTION".to_string()],
+        ..FilterConfig::default()
+    };
+    assert_eq!(output(&dump, &config), "SELECT 1;\n");
+}
+
+#[test]
+fn statements_are_told_apart_by_their_prefix() {
+    let dump: String = STATEMENTS.iter().map(|s| format!("{}\n", s)).collect();
+    let (_, stats) = filtered(&dump, &FilterConfig::default());
+    let lines = |state| stats.included.get(&state).map_or(0, |count| count.lines);
+    assert_eq!(lines(State::Set), 1);
+    assert_eq!(lines(State::SearchPath), 2);
+    assert_eq!(lines(State::Grant), 2);
+    assert_eq!(lines(State::Owner), 2);
+    assert_eq!(lines(State::Ddl), 1);
+}
+
+const INDEXES: &str = "CREATE TABLE public.a (v text);\n\
+CREATE INDEX a_v ON public.a USING btree (v);\n\
+CREATE UNIQUE INDEX a_w\n    ON public.a (v);\n\
+create index a_x on public.a (v);\n\
+CREATE TRIGGER t AFTER INSERT ON public.a FOR EACH ROW EXECUTE FUNCTION public.f();\n\
+COPY public.a (v) FROM stdin;\nCREATE INDEX not_a_statement\n\\.\n";
+
+#[test]
+fn excluded_statements_drop_the_statements_with_that_prefix_ignoring_case() {
+    let config = FilterConfig {
+        excluded_statements: vec![
+            "CREATE INDEX".to_string(),
+            "CREATE UNIQUE INDEX".to_string(),
+        ],
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        output(INDEXES, &config),
+        "CREATE TABLE public.a (v text);\n\
+         CREATE TRIGGER t AFTER INSERT ON public.a FOR EACH ROW EXECUTE FUNCTION public.f();\n\
+         COPY public.a (v) FROM stdin;\nCREATE INDEX not_a_statement\n\\.\n"
+    );
+    let config = FilterConfig {
+        excluded_statements: vec!["create trigger".to_string()],
+        ..FilterConfig::default()
+    };
+    assert!(!output(INDEXES, &config).contains("TRIGGER"));
+}
+
+#[test]
+fn drop_setval_drops_the_setval_calls_even_with_data_only() {
+    let config = FilterConfig {
+        data_only: true,
+        drop_setval: true,
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        output(SCHEMA_AND_DATA, &config),
+        "COPY public.a (v) FROM stdin;\na1\n\\.\n"
+    );
+    let config = FilterConfig {
+        drop_setval: true,
+        ..FilterConfig::default()
+    };
+    assert!(!output(SCHEMA_AND_DATA, &config).contains("setval"));
+}
+
+#[test]
+fn keep_setval_keeps_the_setval_calls_even_with_schema_only() {
+    let config = FilterConfig {
+        schema_only: true,
+        keep_setval: true,
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        output(SCHEMA_AND_DATA, &config),
+        "CREATE TABLE public.a (v text);\n\
+         SELECT pg_catalog.setval('public.s', 1, true);\n"
+    );
+}
+
+#[test]
+fn comment_prefixes_replace_the_dashes() {
+    let dump = "# preprocessed\n/* header */\n-- kept now\nSELECT 1;\n\
+                COPY public.a (v) FROM stdin;\n# a row\n/* another */\n\\.\n";
+    let config = FilterConfig {
+        comment_prefixes: vec!["#".to_string(), "/*".to_string()],
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        output(dump, &config),
+        "-- kept now\nSELECT 1;\nCOPY public.a (v) FROM stdin;\n# a row\n/* another */\n\\.\n"
+    );
+    assert!(pgdump_filter::filter(
+        dump.as_bytes(),
+        std::io::sink(),
+        &FilterConfig {
+            comment_prefixes: vec![String::new()],
+            ..FilterConfig::default()
+        }
+    )
+    .is_err());
+}
+
+#[test]
+fn only_statements_keeps_the_listed_statements_and_the_copy_blocks() {
+    let dump = dump(&[("a", &["a1"]), ("b", &["b1"])]);
+    let config = FilterConfig {
+        only_statements: vec!["create table".to_string()],
+        excluded_copy_blocks: vec!["b".to_string()],
+        ..FilterConfig::default()
+    };
+    assert_eq!(
+        output(&dump, &config),
+        "\nCREATE TABLE public.a (\n    v text\n);\n\n\
+         CREATE TABLE public.b (\n    v text\n);\n\n\
+         COPY public.a (v) FROM stdin;\na1\n\\.\n\n"
+    );
+    let config = FilterConfig {
+        only_statements: vec!["SET ".to_string(), "ALTER TABLE".to_string()],
+        ..FilterConfig::default()
+    };
+    let kept = output(&dump, &config);
+    assert!(!kept.contains("CREATE TABLE"), "{}", kept);
+    assert!(
+        kept.contains("ALTER TABLE public.b OWNER TO me;"),
+        "{}",
+        kept
+    );
+    assert!(kept.contains("SET client_encoding"), "{}", kept);
+}
+
+#[test]
+fn keep_completion_marker_keeps_only_the_dump_complete_comment() {
+    let dump = dump(&[("a", &["a1"])]);
+    let config = FilterConfig {
+        data_only: true,
+        keep_completion_marker: true,
+        ..FilterConfig::default()
+    };
+    let kept = output(&dump, &config);
+    assert!(
+        kept.ends_with("\\.\n-- PostgreSQL database dump complete\n"),
+        "{}",
+        kept
+    );
+    assert!(!kept.contains("-- PostgreSQL database dump\n"), "{}", kept);
+    assert!(!kept.contains("-- Data for Name"), "{}", kept);
+    assert!(!output(&dump, &FilterConfig::default()).contains("dump complete"));
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use common::{dump, output};
+use pgdump_filter::FilterConfig;
+
+/// The entries pg_dump writes for a `serial` column of the table.
+fn sequence_entries(table: &str) -> String {
+    format!(
+        "--\n-- Name: {0}_id_seq; Type: SEQUENCE; Schema: public; Owner: me\n--\n\n\
+         CREATE SEQUENCE public.{0}_id_seq\n    START WITH 1;\n\n\n\
+         --\n-- Name: {0}_id_seq; Type: SEQUENCE OWNED BY; Schema: public; Owner: me\n--\n\n\
+         ALTER SEQUENCE public.{0}_id_seq OWNED BY public.{0}.id;\n\n\n",
+        table
+    )
+}
+
+#[test]
+fn tables_only_matching_data_drops_the_sequence_ownership_of_dropped_tables() {
+    let tables = dump(&[("a", &["a1"]), ("b", &["b1"])]);
+    let start_of_data = tables.find("--\n-- Data for Name").unwrap();
+    let dump = format!(
+        "{}{}{}{}",
+        &tables[..start_of_data],
+        sequence_entries("a"),
+        sequence_entries("b"),
+        &tables[start_of_data..]
+    );
+    let config = FilterConfig {
+        excluded_copy_blocks: vec!["a".to_string()],
+        tables_only_matching_data: true,
+        ..FilterConfig::default()
+    };
+    let output = output(&dump, &config);
+    assert!(!output.contains("OWNED BY public.a.id"), "{}", output);
+    assert!(output.contains("OWNED BY public.b.id"), "{}", output);
+    assert!(!output.contains("CREATE TABLE public.a "), "{}", output);
+}
+
+#[test]
+fn keep_setval_keeps_the_sequence_set_entries_with_use_toc() {
+    let dump = format!(
+        "{}--\n-- Name: a_id_seq; Type: SEQUENCE SET; Schema: public; Owner: me\n--\n\n\
+         SELECT pg_catalog.setval('public.a_id_seq', 1, true);\n\n\n",
+        dump(&[("a", &["a1"])])
+    );
+    let config = FilterConfig {
+        schema_only: true,
+        use_toc: true,
+        keep_setval: true,
+        ..FilterConfig::default()
+    };
+    let output = output(&dump, &config);
+    assert!(output.contains("SELECT pg_catalog.setval"), "{}", output);
+    assert!(!output.contains("a1"), "{}", output);
+}
+
+/// A dump with a function whose body spans lines that read like data and like other statements,
+/// a table with its data and a constraint after the data.
+const SECTIONS: &str = "SET statement_timeout = 0;\n\n\
+--\n-- Name: refresh(); Type: FUNCTION; Schema: public; Owner: me\n--\n\n\
+CREATE FUNCTION public.refresh() RETURNS void\n    LANGUAGE sql\n    AS $$\n\
+SELECT pg_catalog.setval('public.a_id_seq', 1);\n\
+INSERT INTO public.a VALUES (1);\n\
+$$;\n\n\n\
+--\n-- Name: a; Type: TABLE; Schema: public; Owner: me\n--\n\n\
+CREATE TABLE public.a (\n    id integer\n);\n\n\n\
+--\n-- Data for Name: a; Type: TABLE DATA; Schema: public; Owner: me\n--\n\n\
+COPY public.a (id) FROM stdin;\n1\n\\.\n\n\n\
+--\n-- Name: a_id_seq; Type: SEQUENCE SET; Schema: public; Owner: me\n--\n\n\
+SELECT pg_catalog.setval('public.a_id_seq', 1, true);\n\n\n\
+--\n-- Name: a a_pkey; Type: CONSTRAINT; Schema: public; Owner: me\n--\n\n\
+ALTER TABLE ONLY public.a\n    ADD CONSTRAINT a_pkey PRIMARY KEY (id);\n\n\n";
+
+fn sections(data_only: bool) -> String {
+    let config = FilterConfig {
+        use_toc: true,
+        data_only,
+        schema_only: !data_only,
+        ..FilterConfig::default()
+    };
+    output(SECTIONS, &config)
+}
+
+#[test]
+fn use_toc_keeps_the_data_entries_with_data_only() {
+    assert_eq!(
+        sections(true),
+        "COPY public.a (id) FROM stdin;\n1\n\\.\n\
+         SELECT pg_catalog.setval('public.a_id_seq', 1, true);\n"
+    );
+}
+
+#[test]
+fn use_toc_keeps_the_schema_entries_whole_with_schema_only() {
+    assert_eq!(
+        sections(false),
+        "SET statement_timeout = 0;\n\n\
+         CREATE FUNCTION public.refresh() RETURNS void\n    LANGUAGE sql\n    AS $$\n\
+         SELECT pg_catalog.setval('public.a_id_seq', 1);\n\
+         INSERT INTO public.a VALUES (1);\n\
+         $$;\n\n\
+         CREATE TABLE public.a (\n    id integer\n);\n\n\
+         ALTER TABLE ONLY public.a\n    ADD CONSTRAINT a_pkey PRIMARY KEY (id);\n\n"
+    );
+}
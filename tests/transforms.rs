@@ -0,0 +1,53 @@
+//! The transforms are commands of a Unix shell.
+#![cfg(unix)]
+
+mod common;
+
+use common::{dump, output};
+use pgdump_filter::{filter, FilterConfig};
+
+fn transformed(table: &str, command: &str) -> FilterConfig {
+    FilterConfig {
+        transforms: vec![(table.to_string(), command.to_string())],
+        ..FilterConfig::default()
+    }
+}
+
+#[test]
+fn the_rows_of_a_table_go_through_the_transform() {
+    let rows: Vec<String> = (0..5000).map(|i| format!("row {}\tx", i)).collect();
+    let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+    let dump = dump(&[("a", &rows), ("b", &["b1"])]);
+    let expected = output(&dump, &FilterConfig::default());
+    assert_eq!(output(&dump, &transformed("a", "cat")), expected);
+    let upper = output(&dump, &transformed("public.a", "tr a-z A-Z"));
+    assert_eq!(
+        upper,
+        expected.replace("row ", "ROW ").replace("\tx\n", "\tX\n")
+    );
+    assert!(upper.contains("COPY public.a (v) FROM stdin;\nROW 0\tX\n"));
+    assert!(upper.contains("COPY public.b (v) FROM stdin;\nb1\n\\.\n"));
+}
+
+#[test]
+fn a_transform_may_drop_and_add_rows() {
+    let dump = dump(&[("a", &["a1", "a2", "a3"])]);
+    let output = output(&dump, &transformed("a", "grep -v a2; echo a4"));
+    assert!(
+        output.contains("COPY public.a (v) FROM stdin;\na1\na3\na4\n\\.\n"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn a_failing_transform_fails_the_filtering() {
+    let dump = dump(&[("a", &["a1"])]);
+    let error = filter(
+        dump.as_bytes(),
+        Vec::new(),
+        &transformed("a", "cat; exit 3"),
+    )
+    .unwrap_err();
+    assert!(error.to_string().contains("failed"), "{}", error);
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use pgdump_filter::{filter, FilterConfig, Version};
+
+fn versioned(version: &str) -> String {
+    common::dump(&[("a", &["a1"])]).replacen(
+        "--\n\n",
+        &format!(
+            "--\n\n-- Dumped from database version {}\n-- Dumped by pg_dump version 16.1\n\n",
+            version
+        ),
+        1,
+    )
+}
+
+fn min_version(version: &str) -> FilterConfig {
+    FilterConfig {
+        min_version: Some(version.parse().unwrap()),
+        ..FilterConfig::default()
+    }
+}
+
+fn error(dump: &str, config: &FilterConfig) -> String {
+    filter(dump.as_bytes(), &mut Vec::new(), config)
+        .unwrap_err()
+        .to_string()
+}
+
+#[test]
+fn versions_compare_component_by_component() {
+    let v = |s: &str| s.parse::<Version>().unwrap();
+    assert!(v("9.6") < v("10"));
+    assert!(v("14") < v("14.2"));
+    assert!(v("9.6.24") < v("9.10"));
+    assert_eq!(v("14.2").to_string(), "14.2");
+    assert!("14.x".parse::<Version>().is_err());
+}
+
+#[test]
+fn a_dump_at_or_above_the_minimum_version_is_filtered() {
+    let dump = versioned("14.2 (Debian 14.2-1.pgdg110+1)");
+    let expected = common::output(&dump, &FilterConfig::default());
+    assert_eq!(common::output(&dump, &min_version("14.2")), expected);
+    assert_eq!(common::output(&dump, &min_version("13")), expected);
+    assert_eq!(
+        common::output(&versioned("16beta1"), &min_version("16")),
+        common::output(&versioned("16beta1"), &FilterConfig::default())
+    );
+}
+
+#[test]
+fn a_dump_below_the_minimum_version_is_rejected() {
+    let message = error(&versioned("9.6.24"), &min_version("10"));
+    assert!(message.contains("PostgreSQL 9.6.24"), "{}", message);
+    assert!(message.contains("minimum version 10"), "{}", message);
+}
+
+#[test]
+fn a_dump_without_the_version_comment_is_rejected_with_min_version() {
+    let dump = common::dump(&[("a", &["a1"])]);
+    let message = error(&dump, &min_version("10"));
+    assert!(
+        message.contains("Dumped from database version"),
+        "{}",
+        message
+    );
+    assert!(filter(dump.as_bytes(), &mut Vec::new(), &FilterConfig::default()).is_ok());
+}